@@ -0,0 +1,67 @@
+use nalgebra_glm::Vec3;
+
+// Caja alineada a los ejes, usada como volumen envolvente
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Aabb { min, max }
+    }
+
+    // Caja vacía: cualquier unión con ella devuelve la otra caja
+    pub fn empty() -> Self {
+        Aabb {
+            min: Vec3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Vec3::new(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.inf(&other.min),
+            max: self.max.sup(&other.max),
+        }
+    }
+
+    pub fn grow(&mut self, point: &Vec3) {
+        self.min = self.min.inf(point);
+        self.max = self.max.sup(point);
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let d = self.max - self.min;
+        if d.x < 0.0 || d.y < 0.0 || d.z < 0.0 {
+            return 0.0;
+        }
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    // Método de slabs; `inv_direction` es 1/dirección precalculado por rayo.
+    // Devuelve la distancia de entrada si el rayo toca la caja antes de `max_distance`.
+    pub fn hit(&self, ray_origin: &Vec3, inv_direction: &Vec3, max_distance: f32) -> Option<f32> {
+        let mut tmin = 0.0_f32;
+        let mut tmax = max_distance;
+
+        for axis in 0..3 {
+            let t1 = (self.min[axis] - ray_origin[axis]) * inv_direction[axis];
+            let t2 = (self.max[axis] - ray_origin[axis]) * inv_direction[axis];
+            // min/max ignoran NaN (rayo paralelo que pasa justo por el borde)
+            tmin = tmin.max(t1.min(t2));
+            tmax = tmax.min(t1.max(t2));
+        }
+
+        if tmin <= tmax {
+            Some(tmin)
+        } else {
+            None
+        }
+    }
+}
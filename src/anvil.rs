@@ -0,0 +1,362 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::nbt::{self, Tag};
+use crate::structure::{BlockState, ImportError, Structure};
+
+// Lector de mundos guardados en formato Anvil (archivos de región .mca).
+// Soporta el formato de 1.18+ ("sections"/"block_states") y el de 1.13–1.17
+// ("Level"/"Sections"/"Palette"/"BlockStates").
+
+const SECTOR_SIZE: usize = 4096;
+// A partir de esta versión de datos (1.16) los índices ya no cruzan de un long a otro
+const NON_SPANNING_DATA_VERSION: i64 = 2529;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Dimension {
+    Overworld,
+    Nether,
+    End,
+}
+
+impl Dimension {
+    pub fn parse(name: &str) -> Option<Dimension> {
+        match name {
+            "overworld" | "minecraft:overworld" => Some(Dimension::Overworld),
+            "nether" | "the_nether" | "minecraft:the_nether" => Some(Dimension::Nether),
+            "end" | "the_end" | "minecraft:the_end" => Some(Dimension::End),
+            _ => None,
+        }
+    }
+
+    fn region_dir(self, save: &Path) -> PathBuf {
+        match self {
+            Dimension::Overworld => save.join("region"),
+            Dimension::Nether => save.join("DIM-1").join("region"),
+            Dimension::End => save.join("DIM1").join("region"),
+        }
+    }
+}
+
+struct Region {
+    bytes: Vec<u8>,
+}
+
+impl Region {
+    // Datos NBT del chunk (coordenadas locales 0..32), o None si nunca se generó
+    fn chunk(&self, local_x: usize, local_z: usize) -> Result<Option<Tag>, ImportError> {
+        let entry = 4 * (local_x + local_z * 32);
+        let location = self
+            .bytes
+            .get(entry..entry + 4)
+            .ok_or_else(|| format_error("region header is truncated"))?;
+        let offset = u32::from_be_bytes([0, location[0], location[1], location[2]]) as usize * SECTOR_SIZE;
+        if offset == 0 {
+            return Ok(None);
+        }
+
+        let header = self
+            .bytes
+            .get(offset..offset + 5)
+            .ok_or_else(|| format_error("chunk offset points past the end of the region file"))?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let compression = header[4];
+        if compression & 0x80 != 0 {
+            return Err(format_error("chunks stored in external .mcc files are not supported"));
+        }
+        if !matches!(compression, 1..=3) {
+            return Err(format_error(&format!("unsupported chunk compression type {}", compression)));
+        }
+        let data = self
+            .bytes
+            .get(offset + 5..offset + 4 + length)
+            .ok_or_else(|| format_error("chunk data is truncated"))?;
+
+        // nbt::read reconoce gzip (1), zlib (2) y datos sin comprimir (3)
+        Ok(Some(nbt::read(data)?))
+    }
+}
+
+pub struct AnvilWorld {
+    region_dir: PathBuf,
+    regions: HashMap<(i32, i32), Option<Region>>,
+}
+
+impl AnvilWorld {
+    pub fn open(save: &str, dimension: Dimension) -> Result<AnvilWorld, ImportError> {
+        let region_dir = dimension.region_dir(Path::new(save));
+        if !region_dir.is_dir() {
+            return Err(format_error(&format!("no region folder at {}", region_dir.display())));
+        }
+        Ok(AnvilWorld {
+            region_dir,
+            regions: HashMap::new(),
+        })
+    }
+
+    fn region(&mut self, region_x: i32, region_z: i32) -> Result<Option<&Region>, ImportError> {
+        if !self.regions.contains_key(&(region_x, region_z)) {
+            let path = self.region_dir.join(format!("r.{}.{}.mca", region_x, region_z));
+            // Una región que no existe simplemente no tiene bloques
+            let region = match fs::read(&path) {
+                Ok(bytes) => Some(Region { bytes }),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(ImportError::Io(e)),
+            };
+            self.regions.insert((region_x, region_z), region);
+        }
+        Ok(self.regions[&(region_x, region_z)].as_ref())
+    }
+
+    // Recorta la caja [min, max] (inclusive, en coordenadas de bloque del mundo).
+    // Las posiciones del resultado son relativas a `min`.
+    pub fn read_box(&mut self, min: [i32; 3], max: [i32; 3]) -> Result<Structure, ImportError> {
+        let mut blocks = Vec::new();
+
+        for chunk_x in min[0].div_euclid(16)..=max[0].div_euclid(16) {
+            for chunk_z in min[2].div_euclid(16)..=max[2].div_euclid(16) {
+                let Some(region) = self.region(chunk_x.div_euclid(32), chunk_z.div_euclid(32))? else {
+                    continue;
+                };
+                let Some(chunk) = region.chunk(chunk_x.rem_euclid(32) as usize, chunk_z.rem_euclid(32) as usize)?
+                else {
+                    continue;
+                };
+                read_chunk(&chunk, chunk_x, chunk_z, min, max, &mut blocks)
+                    .map_err(|e| format_error(&format!("chunk ({}, {}): {}", chunk_x, chunk_z, e)))?;
+            }
+        }
+
+        Ok(Structure { blocks })
+    }
+}
+
+fn read_chunk(
+    chunk: &Tag,
+    chunk_x: i32,
+    chunk_z: i32,
+    min: [i32; 3],
+    max: [i32; 3],
+    blocks: &mut Vec<([i32; 3], BlockState)>,
+) -> Result<(), ImportError> {
+    let data_version = chunk.get("DataVersion").and_then(Tag::as_i64).unwrap_or(0);
+    let spanning = data_version < NON_SPANNING_DATA_VERSION;
+
+    let sections = chunk
+        .get("sections")
+        .or_else(|| chunk.get("Level").and_then(|level| level.get("Sections")))
+        .and_then(Tag::as_list)
+        .ok_or_else(|| format_error("chunk has no sections"))?;
+
+    for section in sections {
+        let Some(section_y) = section.get("Y").and_then(Tag::as_i64) else {
+            continue;
+        };
+        let base_y = section_y as i32 * 16;
+        if base_y + 15 < min[1] || base_y > max[1] {
+            continue;
+        }
+
+        let (palette, data) = match section.get("block_states") {
+            Some(states) => (states.get("palette"), states.get("data")),
+            None => (section.get("Palette"), section.get("BlockStates")),
+        };
+        // Secciones sin paleta (luz, biomas) no tienen bloques
+        let Some(palette) = palette.and_then(Tag::as_list) else {
+            continue;
+        };
+        let palette = palette.iter().map(BlockState::from_nbt).collect::<Result<Vec<_>, _>>()?;
+        let data = data.and_then(Tag::as_long_array).unwrap_or(&[]);
+        let indices = PackedIndices::new(data, palette.len(), spanning)?;
+
+        for y in min[1].max(base_y)..=max[1].min(base_y + 15) {
+            for z in min[2].max(chunk_z * 16)..=max[2].min(chunk_z * 16 + 15) {
+                for x in min[0].max(chunk_x * 16)..=max[0].min(chunk_x * 16 + 15) {
+                    let index = ((y - base_y) * 256 + z.rem_euclid(16) * 16 + x.rem_euclid(16)) as usize;
+                    let state = palette
+                        .get(indices.get(index))
+                        .ok_or_else(|| format_error("block index outside the section palette"))?;
+                    if !state.is_air() {
+                        blocks.push(([x - min[0], y - min[1], z - min[2]], state.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Índices de paleta empaquetados en un arreglo de longs (4096 por sección)
+struct PackedIndices<'a> {
+    data: &'a [i64],
+    bits: usize,
+    spanning: bool,
+}
+
+impl<'a> PackedIndices<'a> {
+    fn new(data: &'a [i64], palette_len: usize, spanning: bool) -> Result<Self, ImportError> {
+        // Con un solo estado en la paleta el juego omite los datos
+        if palette_len <= 1 {
+            return Ok(PackedIndices { data, bits: 0, spanning });
+        }
+        let bits = (usize::BITS - (palette_len - 1).leading_zeros()).max(4) as usize;
+        let needed = if spanning {
+            (4096 * bits).div_ceil(64)
+        } else {
+            4096_usize.div_ceil(64 / bits)
+        };
+        if data.len() < needed {
+            return Err(format_error(&format!(
+                "block data has {} longs, expected {}",
+                data.len(),
+                needed
+            )));
+        }
+        Ok(PackedIndices { data, bits, spanning })
+    }
+
+    fn get(&self, index: usize) -> usize {
+        if self.bits == 0 {
+            return 0;
+        }
+        let mask = (1_u64 << self.bits) - 1;
+        if self.spanning {
+            let bit = index * self.bits;
+            let (long, offset) = (bit / 64, bit % 64);
+            let mut value = self.data[long] as u64 >> offset;
+            if offset + self.bits > 64 {
+                value |= (self.data[long + 1] as u64) << (64 - offset);
+            }
+            (value & mask) as usize
+        } else {
+            let per_long = 64 / self.bits;
+            let (long, offset) = (index / per_long, (index % per_long) * self.bits);
+            ((self.data[long] as u64 >> offset) & mask) as usize
+        }
+    }
+}
+
+fn format_error(message: &str) -> ImportError {
+    ImportError::Format(message.to_string())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Bloque en la posición relativa `at` del recorte
+    fn block_at(structure: &Structure, at: [i32; 3]) -> Option<&BlockState> {
+        structure.blocks.iter().find(|(position, _)| *position == at).map(|(_, state)| state)
+    }
+
+    fn assert_block(structure: &Structure, at: [i32; 3], name: &str, properties: &[(&str, &str)]) {
+        let state = block_at(structure, at).unwrap_or_else(|| panic!("no block at {:?}", at));
+        assert_eq!(state.name, name, "at {:?}", at);
+        let expected = properties.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        assert_eq!(state.properties, expected, "at {:?}", at);
+    }
+
+    #[test]
+    fn reads_blocks_from_both_sample_regions() {
+        let mut world = AnvilWorld::open("assets/worlds/nether_sample", Dimension::Nether).unwrap();
+        // x de -4 a -1 cae en r.-1.0.mca (1.16, "Level"/"Sections") y de 0 a 4
+        // en r.0.0.mca (1.20, "sections"/"block_states")
+        let structure = world.read_box([-4, 30, 2], [4, 36, 5]).unwrap();
+        assert_eq!(structure.blocks.len(), 95);
+
+        assert_block(&structure, [0, 0, 0], "minecraft:netherrack", &[]);
+        assert_block(&structure, [1, 1, 1], "minecraft:magma_block", &[]);
+        assert_block(&structure, [2, 1, 2], "minecraft:lava", &[("level", "0")]);
+        assert_block(&structure, [0, 2, 0], "minecraft:glowstone", &[]);
+        assert_block(&structure, [3, 4, 1], "minecraft:crying_obsidian", &[]);
+
+        assert_block(&structure, [4, 3, 1], "minecraft:nether_portal", &[("axis", "x")]);
+        assert_block(&structure, [6, 1, 3], "minecraft:lava", &[("level", "0")]);
+        assert_block(&structure, [7, 2, 3], "minecraft:sand", &[]);
+        assert_block(&structure, [6, 6, 1], "minecraft:obsidian", &[]);
+
+        // El aire no se guarda
+        assert!(block_at(&structure, [0, 6, 0]).is_none());
+    }
+
+    #[test]
+    fn missing_regions_have_no_blocks() {
+        let mut world = AnvilWorld::open("assets/worlds/nether_sample", Dimension::Nether).unwrap();
+        let structure = world.read_box([1000, 30, 1000], [1002, 32, 1002]).unwrap();
+        assert!(structure.blocks.is_empty());
+    }
+
+    // Empaqueta los índices como el juego: cruzando longs o no
+    fn pack(values: &[usize], bits: usize, spanning: bool) -> Vec<i64> {
+        let mut data = Vec::new();
+        if spanning {
+            data.resize((values.len() * bits).div_ceil(64), 0_u64);
+            for (index, &value) in values.iter().enumerate() {
+                let bit = index * bits;
+                let (long, offset) = (bit / 64, bit % 64);
+                data[long] |= (value as u64) << offset;
+                if offset + bits > 64 {
+                    data[long + 1] |= (value as u64) >> (64 - offset);
+                }
+            }
+        } else {
+            let per_long = 64 / bits;
+            data.resize(values.len().div_ceil(per_long), 0_u64);
+            for (index, &value) in values.iter().enumerate() {
+                data[index / per_long] |= (value as u64) << ((index % per_long) * bits);
+            }
+        }
+        data.into_iter().map(|long| long as i64).collect()
+    }
+
+    #[test]
+    fn packed_indices_spanning_layout() {
+        // 17 estados: 5 bits, con índices partidos entre dos longs
+        let values: Vec<usize> = (0..4096).map(|i| (i * 7) % 17).collect();
+        let data = pack(&values, 5, true);
+        assert_eq!(data.len(), 320);
+        let indices = PackedIndices::new(&data, 17, true).unwrap();
+        for (index, &value) in values.iter().enumerate() {
+            assert_eq!(indices.get(index), value, "index {}", index);
+        }
+    }
+
+    #[test]
+    fn packed_indices_non_spanning_layout() {
+        // 5 bits: 12 índices por long y 4 bits de relleno
+        let values: Vec<usize> = (0..4096).map(|i| (i * 7) % 17).collect();
+        let data = pack(&values, 5, false);
+        assert_eq!(data.len(), 342);
+        let indices = PackedIndices::new(&data, 17, false).unwrap();
+        for (index, &value) in values.iter().enumerate() {
+            assert_eq!(indices.get(index), value, "index {}", index);
+        }
+    }
+
+    #[test]
+    fn packed_indices_use_at_least_four_bits() {
+        let values: Vec<usize> = (0..4096).map(|i| i % 3).collect();
+        let data = pack(&values, 4, false);
+        let indices = PackedIndices::new(&data, 3, false).unwrap();
+        assert_eq!(indices.get(4094), 4094 % 3);
+    }
+
+    #[test]
+    fn packed_indices_single_entry_palette_needs_no_data() {
+        for spanning in [true, false] {
+            let indices = PackedIndices::new(&[], 1, spanning).unwrap();
+            assert_eq!(indices.get(0), 0);
+            assert_eq!(indices.get(4095), 0);
+        }
+    }
+
+    #[test]
+    fn packed_indices_reject_short_data() {
+        let data = vec![0_i64; 10];
+        assert!(PackedIndices::new(&data, 17, true).is_err());
+        assert!(PackedIndices::new(&data, 17, false).is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use std::f32::consts::PI;
+
+use nalgebra_glm::Vec3;
+
+use crate::material::PbrParams;
+use crate::radiance::Radiance;
+
+// Modelo de microfacetas de Cook-Torrance: distribución GGX, sombreado de
+// Smith (Schlick-GGX) y Fresnel de Schlick. La `roughness` es la perceptual;
+// la distribución usa su cuadrado.
+
+// Reflectancia a incidencia normal: la de los dieléctricos (`f0`) o el color
+// de la superficie en los metales
+pub fn base_reflectance(albedo: Radiance, params: &PbrParams) -> Radiance {
+    Radiance::gray(params.f0) * (1.0 - params.metalness) + albedo * params.metalness
+}
+
+pub fn fresnel_schlick(f0: Radiance, cosine: f32) -> Radiance {
+    let weight = (1.0 - cosine.clamp(0.0, 1.0)).powi(5);
+    f0 * (1.0 - weight) + Radiance::gray(weight)
+}
+
+// Luz reflejada hacia `view` por una luz de radiancia 1 que llega desde
+// `light`, ya multiplicada por el coseno. Las intensidades de las luces se
+// toman como irradiancia (incluyen el factor π), así una superficie blanca
+// mate iluminada de frente devuelve su color, como en Phong.
+pub fn ggx(normal: &Vec3, view: &Vec3, light: &Vec3, albedo: Radiance, params: &PbrParams) -> Radiance {
+    let n_dot_l = normal.dot(light);
+    let n_dot_v = normal.dot(view);
+    if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+        return Radiance::black();
+    }
+    let half = (view + light).normalize();
+    let n_dot_h = normal.dot(&half).max(0.0);
+    let v_dot_h = view.dot(&half).max(0.0);
+
+    // Rugosidad mínima para que las superficies lisas no den brillos infinitos
+    let roughness = params.roughness.clamp(0.045, 1.0);
+    let alpha = roughness * roughness;
+    let alpha2 = alpha * alpha;
+    let d = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    let distribution = alpha2 / (PI * d * d);
+
+    let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    let geometry = (n_dot_v / (n_dot_v * (1.0 - k) + k)) * (n_dot_l / (n_dot_l * (1.0 - k) + k));
+
+    let fresnel = fresnel_schlick(base_reflectance(albedo, params), v_dot_h);
+    let specular = fresnel * (distribution * geometry / (4.0 * n_dot_l * n_dot_v));
+
+    // Lo que no se refleja en la superficie entra y se difunde (salvo en metales)
+    let diffuse = fresnel.map(|f| 1.0 - f) * albedo * ((1.0 - params.metalness) / PI);
+
+    (diffuse + specular) * (PI * n_dot_l)
+}
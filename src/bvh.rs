@@ -0,0 +1,398 @@
+use nalgebra_glm::Vec3;
+
+use crate::aabb::Aabb;
+use crate::ray_intersect::{Intersect, RayIntersect};
+
+const BIN_COUNT: usize = 12;
+const MAX_LEAF_SIZE: usize = 4;
+// Costo relativo de recorrer un nodo frente a intersectar un objeto
+const TRAVERSAL_COST: f32 = 1.0;
+const INTERSECT_COST: f32 = 1.0;
+
+struct BvhNode {
+    bounds: Aabb,
+    // En hojas: primer objeto; en nodos internos: índice del hijo izquierdo
+    // (el derecho siempre es `first + 1`)
+    first: usize,
+    count: usize,
+}
+
+impl BvhNode {
+    fn is_leaf(&self) -> bool {
+        self.count > 0
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Bin {
+    bounds: Aabb,
+    count: usize,
+}
+
+// Jerarquía de volúmenes envolventes construida con SAH por bins
+pub struct Bvh<T> {
+    objects: Vec<T>,
+    nodes: Vec<BvhNode>,
+}
+
+impl<T: RayIntersect> Bvh<T> {
+    pub fn new(objects: Vec<T>) -> Self {
+        let bounds: Vec<Aabb> = objects.iter().map(|o| o.bounds()).collect();
+        let centroids: Vec<Vec3> = bounds.iter().map(|b| b.centroid()).collect();
+        let mut indices: Vec<usize> = (0..objects.len()).collect();
+
+        let mut nodes = Vec::with_capacity(objects.len().max(1) * 2);
+        nodes.push(BvhNode {
+            bounds: Aabb::empty(),
+            first: 0,
+            count: objects.len(),
+        });
+        if !objects.is_empty() {
+            Self::subdivide(&mut nodes, 0, &mut indices, &bounds, &centroids);
+        }
+
+        // Reordenar los objetos para que cada hoja sea un rango contiguo
+        let mut slots: Vec<Option<T>> = objects.into_iter().map(Some).collect();
+        let objects = indices.iter().map(|&i| slots[i].take().unwrap()).collect();
+
+        Bvh { objects, nodes }
+    }
+
+    fn subdivide(
+        nodes: &mut Vec<BvhNode>,
+        node_index: usize,
+        indices: &mut [usize],
+        bounds: &[Aabb],
+        centroids: &[Vec3],
+    ) {
+        let first = nodes[node_index].first;
+        let count = nodes[node_index].count;
+        let range = &mut indices[first..first + count];
+
+        let mut node_bounds = Aabb::empty();
+        let mut centroid_bounds = Aabb::empty();
+        for &i in range.iter() {
+            node_bounds = node_bounds.union(&bounds[i]);
+            centroid_bounds.grow(&centroids[i]);
+        }
+        nodes[node_index].bounds = node_bounds;
+
+        if count <= MAX_LEAF_SIZE {
+            return;
+        }
+
+        let Some((axis, split_bin)) = Self::find_split(range, bounds, centroids, &centroid_bounds, &node_bounds)
+        else {
+            return;
+        };
+
+        let extent = centroid_bounds.max[axis] - centroid_bounds.min[axis];
+        let bin_of = |i: usize| {
+            let relative = (centroids[i][axis] - centroid_bounds.min[axis]) / extent;
+            ((relative * BIN_COUNT as f32) as usize).min(BIN_COUNT - 1)
+        };
+
+        // Partición en sitio: los de bins < split_bin a la izquierda
+        let mut left_count = 0;
+        for j in 0..range.len() {
+            if bin_of(range[j]) < split_bin {
+                range.swap(j, left_count);
+                left_count += 1;
+            }
+        }
+        if left_count == 0 || left_count == count {
+            return;
+        }
+
+        let left_index = nodes.len();
+        nodes.push(BvhNode {
+            bounds: Aabb::empty(),
+            first,
+            count: left_count,
+        });
+        nodes.push(BvhNode {
+            bounds: Aabb::empty(),
+            first: first + left_count,
+            count: count - left_count,
+        });
+        nodes[node_index].first = left_index;
+        nodes[node_index].count = 0;
+
+        Self::subdivide(nodes, left_index, indices, bounds, centroids);
+        Self::subdivide(nodes, left_index + 1, indices, bounds, centroids);
+    }
+
+    // Busca el eje y el bin de corte con menor costo SAH; None si no conviene dividir
+    fn find_split(
+        range: &[usize],
+        bounds: &[Aabb],
+        centroids: &[Vec3],
+        centroid_bounds: &Aabb,
+        node_bounds: &Aabb,
+    ) -> Option<(usize, usize)> {
+        let leaf_cost = INTERSECT_COST * range.len() as f32;
+        let parent_area = node_bounds.surface_area();
+        let mut best: Option<(usize, usize)> = None;
+        let mut best_cost = leaf_cost;
+
+        let extents = centroid_bounds.max - centroid_bounds.min;
+        for (axis, &extent) in extents.iter().enumerate() {
+            if extent <= 0.0 {
+                continue;
+            }
+
+            let mut bins = [Bin { bounds: Aabb::empty(), count: 0 }; BIN_COUNT];
+            for &i in range {
+                let relative = (centroids[i][axis] - centroid_bounds.min[axis]) / extent;
+                let b = ((relative * BIN_COUNT as f32) as usize).min(BIN_COUNT - 1);
+                bins[b].count += 1;
+                bins[b].bounds = bins[b].bounds.union(&bounds[i]);
+            }
+
+            // Barrido desde la derecha para acumular áreas y conteos
+            let mut right_area = [0.0; BIN_COUNT];
+            let mut right_count = [0; BIN_COUNT];
+            let mut accumulated = Aabb::empty();
+            let mut accumulated_count = 0;
+            for b in (1..BIN_COUNT).rev() {
+                accumulated = accumulated.union(&bins[b].bounds);
+                accumulated_count += bins[b].count;
+                right_area[b] = accumulated.surface_area();
+                right_count[b] = accumulated_count;
+            }
+
+            let mut accumulated = Aabb::empty();
+            let mut accumulated_count = 0;
+            for split in 1..BIN_COUNT {
+                accumulated = accumulated.union(&bins[split - 1].bounds);
+                accumulated_count += bins[split - 1].count;
+                if accumulated_count == 0 || right_count[split] == 0 {
+                    continue;
+                }
+                let cost = TRAVERSAL_COST
+                    + INTERSECT_COST
+                        * (accumulated.surface_area() * accumulated_count as f32
+                            + right_area[split] * right_count[split] as f32)
+                        / parent_area;
+                if cost < best_cost {
+                    best_cost = cost;
+                    best = Some((axis, split));
+                }
+            }
+        }
+
+        best
+    }
+
+    // Recorre los nodos cuya caja toca el rayo, del más cercano al más lejano,
+    // llamando a `visit` con cada objeto de las hojas. `visit` devuelve la nueva
+    // distancia máxima, o None para terminar el recorrido.
+    fn traverse<F>(&self, ray_origin: &Vec3, ray_direction: &Vec3, max_distance: f32, mut visit: F)
+    where
+        F: FnMut(&T, f32) -> Option<f32>,
+    {
+        if self.objects.is_empty() {
+            return;
+        }
+
+        let inv_direction = Vec3::new(1.0 / ray_direction.x, 1.0 / ray_direction.y, 1.0 / ray_direction.z);
+        let mut max_distance = max_distance;
+        let mut stack = Vec::with_capacity(64);
+        if self.nodes[0].bounds.hit(ray_origin, &inv_direction, max_distance).is_some() {
+            stack.push(0);
+        }
+
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if node.bounds.hit(ray_origin, &inv_direction, max_distance).is_none() {
+                continue;
+            }
+
+            if node.is_leaf() {
+                for object in &self.objects[node.first..node.first + node.count] {
+                    match visit(object, max_distance) {
+                        Some(distance) => max_distance = distance,
+                        None => return,
+                    }
+                }
+                continue;
+            }
+
+            let left = node.first;
+            let right = node.first + 1;
+            let left_hit = self.nodes[left].bounds.hit(ray_origin, &inv_direction, max_distance);
+            let right_hit = self.nodes[right].bounds.hit(ray_origin, &inv_direction, max_distance);
+
+            // Apilar primero el más lejano para visitar antes el más cercano
+            match (left_hit, right_hit) {
+                (Some(l), Some(r)) => {
+                    if l < r {
+                        stack.push(right);
+                        stack.push(left);
+                    } else {
+                        stack.push(left);
+                        stack.push(right);
+                    }
+                }
+                (Some(_), None) => stack.push(left),
+                (None, Some(_)) => stack.push(right),
+                (None, None) => {}
+            }
+        }
+    }
+}
+
+impl<T: RayIntersect> RayIntersect for Bvh<T> {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3, time: f32) -> Intersect {
+        let mut closest = Intersect::empty();
+
+        self.traverse(ray_origin, ray_direction, f32::INFINITY, |object, max_distance| {
+            let i = object.ray_intersect(ray_origin, ray_direction, time);
+            if i.is_intersecting && i.distance < max_distance {
+                let distance = i.distance;
+                closest = i;
+                Some(distance)
+            } else {
+                Some(max_distance)
+            }
+        });
+
+        closest
+    }
+
+    fn bounds(&self) -> Aabb {
+        match self.nodes.first() {
+            Some(root) if !self.objects.is_empty() => root.bounds,
+            _ => Aabb::empty(),
+        }
+    }
+
+    fn any_hit(&self, ray_origin: &Vec3, ray_direction: &Vec3, max_distance: f32, time: f32) -> bool {
+        let mut occluded = false;
+
+        self.traverse(ray_origin, ray_direction, max_distance, |object, max_distance| {
+            if object.any_hit(ray_origin, ray_direction, max_distance, time) {
+                occluded = true;
+                None
+            } else {
+                Some(max_distance)
+            }
+        });
+
+        occluded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::cube::Cube;
+    use crate::material::Material;
+
+    // Generador reproducible para los casos de prueba (congruencial de 32 bits)
+    struct Rng(u32);
+
+    impl Rng {
+        fn new(seed: u32) -> Rng {
+            Rng(seed.wrapping_mul(2654435761) ^ 0x9e37_79b9)
+        }
+
+        // Número en [0, 1)
+        fn next_f32(&mut self) -> f32 {
+            self.0 = self.0.wrapping_mul(747796405).wrapping_add(2891336453);
+            (self.0 >> 8) as f32 / (1 << 24) as f32
+        }
+    }
+
+    fn random_vec(rng: &mut Rng, scale: f32) -> Vec3 {
+        Vec3::new(rng.next_f32(), rng.next_f32(), rng.next_f32()) * scale
+    }
+
+    fn random_cubes(rng: &mut Rng, count: usize) -> Vec<Cube> {
+        (0..count)
+            .map(|_| {
+                let min = random_vec(rng, 10.0);
+                let size = random_vec(rng, 1.5) + Vec3::repeat(0.05);
+                Cube {
+                    min,
+                    max: min + size,
+                    material: Material::new(Color::new(255, 255, 255), 10.0, [0.9, 0.1], 0.0, 0.0, 0.0),
+                }
+            })
+            .collect()
+    }
+
+    // Distancia al cubo más cercano probando todos, como antes de la BVH
+    fn brute_force(cubes: &[Cube], origin: &Vec3, direction: &Vec3) -> Option<f32> {
+        cubes
+            .iter()
+            .map(|cube| cube.ray_intersect(origin, direction, 0.0))
+            .filter(|i| i.is_intersecting)
+            .map(|i| i.distance)
+            .min_by(f32::total_cmp)
+    }
+
+    #[test]
+    fn matches_brute_force() {
+        let mut rng = Rng::new(7);
+        let cubes = random_cubes(&mut rng, 300);
+        // La misma semilla repite los cubos para la comparación
+        let reference = random_cubes(&mut Rng::new(7), 300);
+        let bvh = Bvh::new(cubes);
+
+        let mut hits = 0;
+        for _ in 0..2000 {
+            // Orígenes dentro y fuera de la nube de cubos
+            let origin = random_vec(&mut rng, 16.0) - Vec3::repeat(3.0);
+            let direction = (random_vec(&mut rng, 2.0) - Vec3::repeat(1.0)).normalize();
+            let max_distance = rng.next_f32() * 12.0;
+
+            let expected = brute_force(&reference, &origin, &direction);
+            let hit = bvh.ray_intersect(&origin, &direction, 0.0);
+            match expected {
+                Some(distance) => {
+                    hits += 1;
+                    assert!(hit.is_intersecting, "missed hit at {}", distance);
+                    assert!((hit.distance - distance).abs() < 1e-4, "{} != {}", hit.distance, distance);
+                }
+                None => assert!(!hit.is_intersecting),
+            }
+
+            let occluded = expected.is_some_and(|distance| distance < max_distance);
+            assert_eq!(bvh.any_hit(&origin, &direction, max_distance, 0.0), occluded);
+        }
+        assert!(hits > 200, "too few rays hit anything ({})", hits);
+    }
+
+    #[test]
+    fn axis_parallel_rays_match_brute_force() {
+        let mut rng = Rng::new(11);
+        let reference = random_cubes(&mut Rng::new(11), 200);
+        let bvh = Bvh::new(random_cubes(&mut rng, 200));
+
+        for _ in 0..500 {
+            let origin = random_vec(&mut rng, 12.0) - Vec3::repeat(1.0);
+            for axis in 0..3 {
+                for sign in [-1.0, 1.0] {
+                    let mut direction = Vec3::zeros();
+                    direction[axis] = sign;
+                    let expected = brute_force(&reference, &origin, &direction);
+                    let hit = bvh.ray_intersect(&origin, &direction, 0.0);
+                    assert_eq!(hit.is_intersecting, expected.is_some());
+                    if let Some(distance) = expected {
+                        assert!((hit.distance - distance).abs() < 1e-4);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty_bvh_never_hits() {
+        let bvh: Bvh<Cube> = Bvh::new(Vec::new());
+        let direction = Vec3::new(0.0, 0.0, -1.0);
+        assert!(!bvh.ray_intersect(&Vec3::zeros(), &direction, 0.0).is_intersecting);
+        assert!(!bvh.any_hit(&Vec3::zeros(), &direction, f32::INFINITY, 0.0));
+    }
+}
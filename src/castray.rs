@@ -1,17 +1,39 @@
 use nalgebra_glm::Vec3;
-use std::f32::INFINITY;
 
 
 use crate::ray_intersect::RayIntersect;
 
+use crate::brdf;
+use crate::material::ShadingModel;
 use crate::Intersect;
 use crate::Light;
-use crate::Cube;
 use crate::Color;
+use crate::pathtrace::Emitters;
+use crate::radiance::Radiance;
 
 const ORIGIN_BIAS: f32 = 1e-4;
+const SHADOW_BIAS: f32 = 1e-3;
+// Superficies transparentes que atraviesa un rayo de sombra antes de rendirse
+const MAX_SHADOW_LAYERS: u32 = 8;
+const MIN_TRANSMITTANCE: f32 = 1e-3;
+
+// Lo que comparten todos los rayos de un cuadro
+pub struct RenderContext<'a> {
+    pub objects: &'a dyn RayIntersect,
+    pub daylight: &'a Light,
+    pub lights: &'a [Light],
+    // Caras de los bloques emisivos, que el trazado de caminos muestrea como luces
+    pub emitters: &'a Emitters,
+    // Instante de la animación de las texturas, en segundos
+    pub time: f32,
+    // Apertura de un pixel en radianes: el rayo es un cono que se ensancha con
+    // la distancia, y su ancho decide el mipmap de las texturas
+    pub pixel_spread: f32,
+    // Modelo de sombreado para todos los materiales (None: el de cada uno)
+    pub shading: Option<ShadingModel>,
+}
 
-fn offset_origin(intersect: &Intersect, direction: &Vec3) -> Vec3{
+pub fn offset_origin(intersect: &Intersect, direction: &Vec3) -> Vec3{
     let offset = intersect.normal * ORIGIN_BIAS;
     if direction.dot(&intersect.normal) < 0.0 {
         intersect.point - offset
@@ -20,8 +42,8 @@ fn offset_origin(intersect: &Intersect, direction: &Vec3) -> Vec3{
     }
 }
 
-fn refract(incident: &Vec3, normal: &Vec3, eta_t: f32) -> Vec3 {
-    let cosi = -incident.dot(normal).max(-1.0).min(1.0);
+pub fn refract(incident: &Vec3, normal: &Vec3, eta_t: f32) -> Vec3 {
+    let cosi = -incident.dot(normal).clamp(-1.0, 1.0);
 
     let (n_cosi, eta, n_normal);
 
@@ -44,117 +66,205 @@ fn refract(incident: &Vec3, normal: &Vec3, eta_t: f32) -> Vec3 {
     }
 }
 
-fn reflect(incident: &Vec3, normal: &Vec3) -> Vec3{
+pub fn reflect(incident: &Vec3, normal: &Vec3) -> Vec3{
     incident - 2.0 * incident.dot(normal) * normal
 }
 
-fn cast_shadow(
+// Luz que deja pasar el camino hasta la luz: blanco sin sombra, negro sombra
+// completa. Las luces con superficie (y el sol con disco) lanzan
+// `Light::sample_count` rayos hacia distintos puntos de la luz y los valores
+// intermedios forman la penumbra.
+pub fn cast_shadow(
     intersect: &Intersect,
     light: &Light,
-    objects: &[Cube],
-) -> f32 {
-    let light_dir = (light.position - intersect.point).normalize();
-    let shadow_ray_origin = offset_origin(intersect, &light_dir); // Usar offset para evitar auto-sombra
-    let light_distance = (light.position - shadow_ray_origin).magnitude(); // Distancia a la luz
-
-    for object in objects {
-        let shadow_intersect = object.ray_intersect(&shadow_ray_origin, &light_dir);
-
-        // Verificar si el objeto está entre el punto y la luz
-        if shadow_intersect.is_intersecting && shadow_intersect.distance < light_distance {
-            return 1.0; // Sombra completa
+    objects: &dyn RayIntersect,
+    time: f32,
+) -> Radiance {
+    let samples = light.sample_count();
+    let mut visible = Radiance::black();
+
+    for index in 0..samples {
+        let (light_dir, distance) = light.shadow_ray(&intersect.point, index);
+        let shadow_ray_origin = offset_origin(intersect, &light_dir); // Usar offset para evitar auto-sombra
+        // Se corta justo antes de la luz: en las luces de bloque el punto está
+        // sobre la cara del propio cubo emisivo. La luz direccional no tiene
+        // fin y cualquier objeto en el camino la tapa.
+        let light_distance = distance - SHADOW_BIAS;
+
+        // Verificar si algún objeto está entre el punto y la luz
+        if objects.any_hit(&shadow_ray_origin, &light_dir, light_distance, time) {
+            visible += transmittance(&shadow_ray_origin, &light_dir, light_distance, objects, time);
+        } else {
+            visible += Radiance::gray(1.0);
         }
     }
 
-    0.0 // Sin sombra
+    visible * (1.0 / samples as f32)
 }
 
+// Luz que atraviesa los objetos entre `origin` y `max_distance`. Cada
+// superficie transparente (vidrio de color, hielo, portal) deja pasar su
+// `transparency` teñida con su color; una opaca corta la luz.
+fn transmittance(
+    origin: &Vec3,
+    direction: &Vec3,
+    max_distance: f32,
+    objects: &dyn RayIntersect,
+    time: f32,
+) -> Radiance {
+    let mut transmitted = Radiance::gray(1.0);
+    let mut origin = *origin;
+    let mut remaining = max_distance;
+
+    for _ in 0..MAX_SHADOW_LAYERS {
+        let hit = objects.ray_intersect(&origin, direction, time);
+        if !hit.is_intersecting || hit.distance >= remaining {
+            return transmitted;
+        }
+        let transparency = hit.material.transparency;
+        if transparency <= 0.0 {
+            return Radiance::black();
+        }
+        // El tinte conserva el tono del material pero no su brillo, así un
+        // vidrio oscuro no apaga más la luz que uno claro de la misma transparencia
+        let (u, v) = hit.uv;
+        let color = hit.material.get_diffuse_color(u, v, time, 0.0);
+        let peak = color.r.max(color.g).max(color.b);
+        let tint = if peak > 0.0 { color * (1.0 / peak) } else { Radiance::gray(1.0) };
+        transmitted = transmitted * tint * transparency;
+        if transmitted.average() < MIN_TRANSMITTANCE {
+            return Radiance::black();
+        }
 
+        origin = hit.point + direction * ORIGIN_BIAS;
+        remaining -= hit.distance + ORIGIN_BIAS;
+    }
+
+    // Demasiadas capas: se toma como sombra completa
+    Radiance::black()
+}
+
+
+// `cone_width` es el ancho del cono del rayo en su origen (0 desde la cámara)
 pub fn cast_ray(
     ray_origin: &Vec3, 
     ray_direction: &Vec3, 
-    objects: &[Cube], 
-    daylight: &Light, 
-    other_lights: &[Light], 
-    depth: u32
-) -> Color {
+    context: &RenderContext,
+    depth: u32,
+    cone_width: f32,
+) -> Radiance {
+    let RenderContext { objects, daylight, lights: other_lights, time, .. } = *context;
 
     if depth > 3 {
-        return Color::new(179, 179, 179); // Color de fondo si se alcanza la profundidad máxima
+        return Radiance::from_srgb(Color::new(179, 179, 179)); // Color de fondo si se alcanza la profundidad máxima
     }
 
-    let mut intersect = Intersect::empty();
-    let mut zbuffer = f32::INFINITY; // Inicializar el zbuffer con el valor máximo
-
-    // Bucle para encontrar la intersección más cercana
-    for object in objects {
-        let i = object.ray_intersect(&ray_origin, &ray_direction);
-        if i.is_intersecting && i.distance < zbuffer {
-            zbuffer = i.distance; // Actualizar el zbuffer con la distancia más cercana
-            intersect = i; // Actualizar el objeto de intersección
-        }
-    }
+    // Intersección más cercana (la BVH descarta los objetos que el rayo no toca)
+    let intersect = objects.ray_intersect(ray_origin, ray_direction, time);
 
     // Si no hay intersección, devolver el color de fondo
     if !intersect.is_intersecting {
         // Cambiar el color de fondo dependiendo de las propiedades de daylight
         let background_color = calculate_background_color(daylight);
-        return background_color;
+        return Radiance::from_srgb(background_color);
     }
+    // Ancho del cono al llegar a la superficie, medido sobre ella: se estira
+    // cuanto más rasante es el rayo
+    let hit_width = cone_width + context.pixel_spread * intersect.distance;
+    let cosine = intersect.normal.dot(ray_direction).abs().max(0.05);
+    let footprint = hit_width * intersect.uv_density / cosine;
+
+    let (u, v) = intersect.uv;
+    let diffuse_color = intersect.material.get_diffuse_color(u, v, time, footprint);
+    let model = context.shading.unwrap_or(intersect.material.model);
+    let pbr = intersect.material.pbr_params(u, v, time, footprint);
+    let view_dir = (ray_origin - intersect.point).normalize();
+
     // Función auxiliar para calcular la luz total
     let calculate_light_intensity = |light: &Light| {
-        let light_dir = (light.position - intersect.point).normalize();
-        let view_dir = (ray_origin - intersect.point).normalize();
+        // Fuera del alcance o del cono de la luz no hace falta lanzar rayos de sombra
+        let attenuation = light.falloff(&intersect.point);
+        if attenuation <= 0.0 {
+            return Radiance::black();
+        }
+        let (light_dir, _) = light.incidence(&intersect.point);
         let reflect_dir = reflect(&-light_dir, &intersect.normal).normalize();
         
-        // Intensidad de la sombra
-        let shadow_intensity = cast_shadow(&intersect, light, objects);
-        let light_intensity = light.intensity * (1.0 - shadow_intensity);
+        // Luz que llega después de las sombras, teñida por lo transparente
+        let visibility = cast_shadow(&intersect, light, objects, time);
+        if visibility.average() <= 0.0 {
+            return Radiance::black();
+        }
+        let light_intensity = light.intensity * attenuation;
+        let light_color = Radiance::from_srgb(light.color);
+
+        if model == ShadingModel::Pbr {
+            let reflected = brdf::ggx(&intersect.normal, &view_dir, &light_dir, diffuse_color, &pbr);
+            return reflected * light_color * visibility * light_intensity;
+        }
         
         // Cálculo de iluminación difusa
         let diffuse_intensity = intersect.normal.dot(&light_dir).max(0.0);
-        let diffuse_color = intersect.material.get_diffuse_color(intersect.uv.0, intersect.uv.1);
-        let diffuse = ((light.color * 0.09) + diffuse_color) * intersect.material.albedo[0] * diffuse_intensity * light_intensity;
+        let diffuse = ((light_color * 0.09) + diffuse_color) * intersect.material.albedo[0] * diffuse_intensity * light_intensity;
 
         // Cálculo de iluminación especular
         let specular_intensity = view_dir.dot(&reflect_dir).max(0.0).powf(intersect.material.spec);
-        let specular = light.color * intersect.material.albedo[1] * specular_intensity * light_intensity;
+        let specular = light_color * intersect.material.albedo[1] * specular_intensity * light_intensity;
 
         // Combinar difuso y especular
-        diffuse + specular
+        (diffuse + specular) * visibility
     };
 
     // Calcular la luz total (luz del día + otras fuentes de luz)
     let mut total_light = calculate_light_intensity(daylight);
     for light in other_lights {
-        total_light = total_light + calculate_light_intensity(light);
+        total_light += calculate_light_intensity(light);
     }
 
     // Color final (sin reflejos y refracciones)
     let reflectivity = intersect.material.reflectivity;
     let transparency = intersect.material.transparency;
 
+    // En PBR la superficie además refleja el entorno como un espejo, con un
+    // peso según Fresnel que baja con la rugosidad: una aproximación del
+    // brillo especular indirecto. Lo que se refleja ya no llega a la luz
+    // directa, así la suma no devuelve más energía de la que recibe.
+    let mut reflectance = Radiance::gray(reflectivity);
+    let mut direct_weight = Radiance::gray(1.0 - reflectivity - transparency);
+    if model == ShadingModel::Pbr {
+        let f0 = brdf::base_reflectance(diffuse_color, &pbr);
+        let glossy = brdf::fresnel_schlick(f0, intersect.normal.dot(&view_dir)) * (1.0 - pbr.roughness).powi(2);
+        direct_weight = direct_weight * glossy.map(|f| 1.0 - f);
+        reflectance += glossy;
+    }
+
     // Reflejos
-    let mut reflect_color = Color::black();
-    if reflectivity > 0.0 {
-        let reflect_dir = reflect(&ray_direction, &intersect.normal).normalize();
+    let mut reflect_color = Radiance::black();
+    if reflectance.average() > 0.0 {
+        let reflect_dir = reflect(ray_direction, &intersect.normal).normalize();
         let reflect_origin = intersect.point + intersect.normal * 0.001; // Ajustar el origen del rayo
-        reflect_color = cast_ray(&reflect_origin, &reflect_dir, objects, daylight, other_lights, depth + 1);
+        reflect_color = cast_ray(&reflect_origin, &reflect_dir, context, depth + 1, hit_width);
     }
 
     // Refracción
-    let mut refract_color = Color::black();
+    let mut refract_color = Radiance::black();
     if transparency > 0.0 {
-        let refract_dir = refract(&ray_direction, &intersect.normal, intersect.material.refraction_index).normalize();
+        let refract_dir = refract(ray_direction, &intersect.normal, intersect.material.refraction_index).normalize();
         let refract_origin = offset_origin(&intersect, &refract_dir);
-        refract_color = cast_ray(&refract_origin, &refract_dir, objects, daylight, other_lights, depth + 1);
+        refract_color = cast_ray(&refract_origin, &refract_dir, context, depth + 1, hit_width);
+    }
+
+    // Luz propia del material, independiente de las luces de la escena
+    let mut emission = intersect.material.get_emission(u, v, time, footprint);
+    if model == ShadingModel::Pbr {
+        emission += diffuse_color * pbr.emission;
     }
 
     // Combinación final de colores
-    total_light * (1.0 - reflectivity - transparency) + (reflect_color * reflectivity) + (refract_color * transparency)
+    emission + total_light * direct_weight + (reflect_color * reflectance) + (refract_color * transparency)
 }
 
-fn calculate_background_color(daylight: &Light) -> Color {
+pub fn calculate_background_color(daylight: &Light) -> Color {
     let angle = daylight.day_angle;
 
     const DAY_START: f32 = 0.0;                           // Amanecer
@@ -166,7 +276,7 @@ fn calculate_background_color(daylight: &Light) -> Color {
     let g: u8;
     let b: u8;
 
-    if angle >= DAY_START && angle < DAY_MID {
+    if (DAY_START..DAY_MID).contains(&angle) {
         // Gradiente de azul oscuro a amarillo-naranja y luego a celeste (Amanecer)
         let ratio = angle / (DAY_MID - DAY_START);
         
@@ -183,13 +293,13 @@ fn calculate_background_color(daylight: &Light) -> Color {
             b = (30.0 * (1.0 - sub_ratio) + 250.0 * sub_ratio) as u8; // Naranja (30) a celeste (250)
         }
 
-    } else if angle >= DAY_MID && angle < DAY_END {
+    } else if (DAY_MID..DAY_END).contains(&angle) {
         // Día: Celeste
         r = 135;
         g = 206;
         b = 250;
 
-    } else if angle >= DAY_END && angle < NIGHT_START {
+    } else if (DAY_END..NIGHT_START).contains(&angle) {
         // Gradiente de celeste a amarillo-naranja y luego a azul oscuro (Atardecer)
         let ratio = (angle - DAY_END) / (NIGHT_START - DAY_END);
         
@@ -0,0 +1,157 @@
+use nalgebra_glm::Vec3;
+
+use crate::material::ShadingModel;
+use crate::pathtrace::Integrator;
+use crate::tonemap::ToneMapper;
+
+pub const USAGE: &str = "\
+Usage: MinecraftDiorama [SCENE] [OPTIONS]
+
+Arguments:
+  SCENE                  Scene file (default: ./assets/scenes/nether_portal.json)
+
+Options:
+  --headless             Render a single frame to a PNG file instead of opening a window
+  --output PATH          Output image for --headless (default: render.png)
+  --width N              Image width in pixels (default: 1000)
+  --height N             Image height in pixels (default: 550)
+  --eye X,Y,Z            Camera position (overrides the scene)
+  --center X,Y,Z         Point the camera looks at (overrides the scene)
+  --day-angle RADIANS    Time of day as the sun angle (overrides the scene)
+  --time SECONDS         Animation time for animated textures (default: 0)
+  --tonemap NAME         Tone mapping operator: clamp, reinhard or aces (default: clamp)
+  --exposure EV          Exposure in stops, applied before tone mapping (default: 0)
+  --shading MODEL        Force phong or pbr shading on every material (default: per material)
+  --dither               Apply ordered dithering when encoding the image to 8-bit sRGB
+  --integrator NAME      Rendering algorithm: whitted or path (default: whitted)
+  --samples N            Path tracing passes for --headless (default: 64)
+  -h, --help             Show this help";
+
+// Opciones de línea de comandos
+pub struct Options {
+    pub scene: String,
+    pub headless: bool,
+    pub output: String,
+    pub width: usize,
+    pub height: usize,
+    pub eye: Option<Vec3>,
+    pub center: Option<Vec3>,
+    pub day_angle: Option<f32>,
+    pub time: f32,
+    pub tonemap: ToneMapper,
+    pub exposure: f32,
+    pub shading: Option<ShadingModel>,
+    pub dither: bool,
+    pub integrator: Integrator,
+    pub samples: usize,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            scene: "./assets/scenes/nether_portal.json".to_string(),
+            headless: false,
+            output: "render.png".to_string(),
+            width: 1000,
+            height: 550,
+            eye: None,
+            center: None,
+            day_angle: None,
+            time: 0.0,
+            tonemap: ToneMapper::Clamp,
+            exposure: 0.0,
+            shading: None,
+            dither: false,
+            integrator: Integrator::Whitted,
+            samples: 64,
+            help: false,
+        }
+    }
+}
+
+impl Options {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut scene = None;
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or_else(|| format!("missing value for {}", name));
+            match arg.as_str() {
+                "--headless" => options.headless = true,
+                "--dither" => options.dither = true,
+                "--output" | "-o" => options.output = value(&arg)?,
+                "--width" => options.width = parse_size(&arg, &value(&arg)?)?,
+                "--height" => options.height = parse_size(&arg, &value(&arg)?)?,
+                "--eye" => options.eye = Some(parse_vec3(&arg, &value(&arg)?)?),
+                "--center" => options.center = Some(parse_vec3(&arg, &value(&arg)?)?),
+                "--day-angle" => {
+                    let text = value(&arg)?;
+                    let angle = text
+                        .parse()
+                        .map_err(|_| format!("invalid value for --day-angle: `{}`", text))?;
+                    options.day_angle = Some(angle);
+                }
+                "--time" => {
+                    let text = value(&arg)?;
+                    options.time = text
+                        .parse()
+                        .map_err(|_| format!("invalid value for --time: `{}`", text))?;
+                }
+                "--tonemap" => {
+                    let text = value(&arg)?;
+                    options.tonemap = ToneMapper::parse(&text)
+                        .ok_or_else(|| format!("unknown tone mapping operator `{}`", text))?;
+                }
+                "--exposure" => {
+                    let text = value(&arg)?;
+                    options.exposure = text
+                        .parse()
+                        .map_err(|_| format!("invalid value for --exposure: `{}`", text))?;
+                }
+                "--shading" => {
+                    let text = value(&arg)?;
+                    let model = ShadingModel::parse(&text)
+                        .ok_or_else(|| format!("unknown shading model `{}`", text))?;
+                    options.shading = Some(model);
+                }
+                "--integrator" => {
+                    let text = value(&arg)?;
+                    options.integrator = Integrator::parse(&text)
+                        .ok_or_else(|| format!("unknown integrator `{}`", text))?;
+                }
+                "--samples" => options.samples = parse_size(&arg, &value(&arg)?)?,
+                "--help" | "-h" => options.help = true,
+                _ if arg.starts_with('-') => return Err(format!("unknown option `{}`", arg)),
+                _ if scene.is_none() => scene = Some(arg),
+                _ => return Err(format!("unexpected argument `{}`", arg)),
+            }
+        }
+
+        if let Some(scene) = scene {
+            options.scene = scene;
+        }
+        Ok(options)
+    }
+}
+
+fn parse_size(name: &str, text: &str) -> Result<usize, String> {
+    match text.parse() {
+        Ok(size) if size > 0 => Ok(size),
+        _ => Err(format!("invalid value for {}: `{}`", name, text)),
+    }
+}
+
+// "x,y,z"
+fn parse_vec3(name: &str, text: &str) -> Result<Vec3, String> {
+    let values: Vec<f32> = text
+        .split(',')
+        .map(|v| v.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("invalid value for {}: `{}`, expected X,Y,Z", name, text))?;
+    match values[..] {
+        [x, y, z] => Ok(Vec3::new(x, y, z)),
+        _ => Err(format!("invalid value for {}: `{}`, expected X,Y,Z", name, text)),
+    }
+}
@@ -1,6 +1,6 @@
 use std::ops::{Add, Mul};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -58,7 +58,7 @@ impl Color {
         Color {r: 0, g: 0, b: 0}
     }
 
-    pub fn to_u32(&self) -> u32 {
+    pub fn to_u32(self) -> u32 {
         // Asumimos que `self` tiene componentes `r`, `g`, `b` como u8
         let r = (self.r as u32) << 16;  // Shift para ocupar el espacio del rojo en u32
         let g = (self.g as u32) << 8;   // Shift para ocupar el espacio del verde en u32
@@ -1,4 +1,5 @@
-use nalgebra_glm::{Vec3, dot};
+use nalgebra_glm::Vec3;
+use crate::aabb::Aabb;
 use crate::ray_intersect::{Intersect, RayIntersect};
 use crate::material::Material;
 
@@ -9,7 +10,7 @@ pub struct Cube {
 }
 
 impl RayIntersect for Cube {
-    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3, time: f32) -> Intersect {
         let mut tmin = (self.min.x - ray_origin.x) / ray_direction.x;
         let mut tmax = (self.max.x - ray_origin.x) / ray_direction.x;
 
@@ -57,22 +58,68 @@ impl RayIntersect for Cube {
 
         // Si tmin es positivo, es una intersección en la dirección del rayo
         if tmin > 0.0 {
-            let point = ray_origin + ray_direction * tmin;
-            let normal = self.calculate_normal(&point); // Calcula la normal en el punto de intersección
-            let distance = tmin;
+            if let Some(hit) = self.surface_hit(ray_origin, ray_direction, tmin, time) {
+                return hit;
+            }
+        }
 
-            // Calculate UV coordinates
-            let (u, v) = self.calculate_uv(&point);
-            
-            return Intersect::new(point, normal, distance, self.material.clone(), (u, v));
+        // Con recorte por alfa el rayo puede pasar por un texel transparente
+        // de la cara de entrada y tocar la cara de salida
+        if self.material.alpha_cutoff.is_some() && tmax > 0.0 {
+            if let Some(hit) = self.surface_hit(ray_origin, ray_direction, tmax, time) {
+                return hit;
+            }
         }
 
         Intersect::empty()
     }
+
+    fn bounds(&self) -> Aabb {
+        Aabb::new(self.min, self.max)
+    }
 }
 
 
 impl Cube {
+    // Intersección con la superficie a distancia `t`, o None si el texel está recortado
+    fn surface_hit(&self, ray_origin: &Vec3, ray_direction: &Vec3, t: f32, time: f32) -> Option<Intersect> {
+        let point = ray_origin + ray_direction * t;
+        let mut normal = self.calculate_normal(&point); // Calcula la normal en el punto de intersección
+
+        // La cara se elige a partir de la normal; su textura (si tiene una
+        // propia) reemplaza a la del material, con su rotación y recorte
+        let face = Face::from_normal(&normal);
+        let (material, uv) = self.material.for_face(face, self.calculate_uv(&point, face));
+        if material.is_cut_out(uv.0, uv.1, time) {
+            return None;
+        }
+
+        // Relieve (mapas de normales y alturas) sobre la normal geométrica
+        let uv_density = self.uv_density(&normal, face);
+        let (shading_normal, uv) = material.relief(&normal, self.material.face_tangents(face), uv, ray_direction, time);
+        normal = shading_normal;
+
+        // Las superficies recortadas son delgadas (hojas, vidrio): por detrás
+        // se ven igual que por delante
+        if material.alpha_cutoff.is_some() && normal.dot(ray_direction) > 0.0 {
+            normal = -normal;
+        }
+
+        Some(
+            Intersect::new(point, normal, t, material, uv)
+                .with_uv_density(uv_density)
+                .with_emitter(self.material.is_emissive()),
+        )
+    }
+
+    // Texels por unidad de superficie: la parte de la textura que muestra la
+    // cara repartida sobre su área
+    fn uv_density(&self, normal: &Vec3, face: Face) -> f32 {
+        let size = self.max - self.min;
+        let face_area = size.x * size.y * size.z / size.dot(&normal.abs());
+        (self.material.face_uv_area(face) / face_area).sqrt()
+    }
+
     fn calculate_normal(&self, point: &Vec3) -> Vec3 {
         // Comparamos el punto de intersección con las caras del cubo para determinar la normal
         let epsilon = 1e-4; // Un pequeño valor para la precisión
@@ -94,41 +141,99 @@ impl Cube {
         Vec3::new(0.0, 0.0, 0.0) // Normal por defecto (si no se encuentra coincidencia)
     }
 
-    fn calculate_uv(&self, point: &Vec3) -> (f32, f32) {
-        let epsilon = 1e-4;
-    
-        if (point.x - self.min.x).abs() < epsilon {
-            // Cara izquierda (eje X negativo)
-            let u = (point.z - self.min.z) / (self.max.z - self.min.z);
-            let v = (self.max.y - point.y) / (self.max.y - self.min.y); 
-            return(u, v);
-        } else if (point.x - self.max.x).abs() < epsilon {
-            // Cara derecha (eje X positivo)
-            let u = (point.z - self.min.z) / (self.max.z - self.min.z);
-            let v = (self.max.y - point.y) / (self.max.y - self.min.y); 
-            return(u, v);
-        } else if (point.y - self.min.y).abs() < epsilon {
-            // Cara inferior (eje Y negativo)
-            let u = (point.x - self.min.x) / (self.max.x - self.min.x);
-            let v = (point.z - self.min.z) / (self.max.z - self.min.z);
-            return(u, v);
-        } else if (point.y - self.max.y).abs() < epsilon {
-            // Cara superior (eje Y positivo) - Ya funciona bien
-            let u = (point.x - self.min.x) / (self.max.x - self.min.x);
-            let v = (point.z - self.min.z) / (self.max.z - self.min.z);
-            return(u, v);
-        } else if (point.z - self.min.z).abs() < epsilon {
-            // Cara trasera (eje Z negativo)
-            let u = (self.max.x - point.x) / (self.max.x - self.min.x);
-            let v = (self.max.y - point.y) / (self.max.y - self.min.y);
-            return(u, v);
+    fn calculate_uv(&self, point: &Vec3, face: Face) -> (f32, f32) {
+        face.uv_at(&(point - self.min).component_div(&(self.max - self.min)))
+    }
+}
+
+// Caras de un bloque, con los nombres que usa Minecraft
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Face {
+    Down,
+    Up,
+    North,
+    South,
+    West,
+    East,
+}
+
+impl Face {
+    pub const ALL: [Face; 6] = [Face::Down, Face::Up, Face::North, Face::South, Face::West, Face::East];
+
+    pub fn from_normal(normal: &Vec3) -> Face {
+        let abs = normal.abs();
+        if abs.x >= abs.y && abs.x >= abs.z {
+            if normal.x < 0.0 { Face::West } else { Face::East }
+        } else if abs.y >= abs.z {
+            if normal.y < 0.0 { Face::Down } else { Face::Up }
+        } else if normal.z < 0.0 {
+            Face::North
         } else {
-            // Cara frontal (eje Z positivo) - Ya funciona bien
-            let u = (point.x - self.min.x) / (self.max.x - self.min.x);
-            let v = (self.max.y - point.y) / (self.max.y - self.min.y);
-            return(u, v);
+            Face::South
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Face> {
+        match name {
+            "down" | "bottom" => Some(Face::Down),
+            "up" | "top" => Some(Face::Up),
+            "north" => Some(Face::North),
+            "south" => Some(Face::South),
+            "west" => Some(Face::West),
+            "east" => Some(Face::East),
+            _ => None,
         }
+    }
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn normal(self) -> Vec3 {
+        match self {
+            Face::Down => Vec3::new(0.0, -1.0, 0.0),
+            Face::Up => Vec3::new(0.0, 1.0, 0.0),
+            Face::North => Vec3::new(0.0, 0.0, -1.0),
+            Face::South => Vec3::new(0.0, 0.0, 1.0),
+            Face::West => Vec3::new(-1.0, 0.0, 0.0),
+            Face::East => Vec3::new(1.0, 0.0, 0.0),
+        }
+    }
+
+    // UV de un punto de la cara, en coordenadas relativas a la caja [0, 1], con
+    // la orientación de Minecraft: vista desde afuera, u crece hacia la derecha
+    // y v hacia arriba (en la cara superior, "arriba" es el norte, -z)
+    pub fn uv_at(self, p: &Vec3) -> (f32, f32) {
+        match self {
+            Face::West => (p.z, p.y),        // Cara izquierda (eje X negativo)
+            Face::East => (1.0 - p.z, p.y),  // Cara derecha (eje X positivo)
+            Face::Down => (p.x, p.z),        // Cara inferior (eje Y negativo)
+            Face::Up => (p.x, 1.0 - p.z),    // Cara superior (eje Y positivo)
+            Face::North => (1.0 - p.x, p.y), // Cara trasera (eje Z negativo)
+            Face::South => (p.x, p.y),       // Cara frontal (eje Z positivo)
+        }
+    }
+
+    // Direcciones en las que crecen u y v sobre la cara (ver `uv_at`)
+    pub fn uv_axes(self) -> (Vec3, Vec3) {
+        let y = Vec3::new(0.0, 1.0, 0.0);
+        match self {
+            Face::West => (Vec3::new(0.0, 0.0, 1.0), y),
+            Face::East => (Vec3::new(0.0, 0.0, -1.0), y),
+            Face::Down => (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+            Face::Up => (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -1.0)),
+            Face::North => (Vec3::new(-1.0, 0.0, 0.0), y),
+            Face::South => (Vec3::new(1.0, 0.0, 0.0), y),
+        }
+    }
+}
 
-        (0.0, 0.0) // Default UV if no face is matched
+// Rota las coordenadas UV en sentido horario, en pasos de 90 grados
+pub fn rotate_uv((u, v): (f32, f32), rotation: u16) -> (f32, f32) {
+    match rotation {
+        90 => (1.0 - v, u),
+        180 => (1.0 - u, 1.0 - v),
+        270 => (v, 1.0 - u),
+        _ => (u, v),
     }
 }
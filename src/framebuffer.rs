@@ -1,3 +1,4 @@
+use image::{ImageResult, Rgb, RgbImage};
 use nalgebra::Vector3;
 use crate::Color;
 
@@ -5,6 +6,8 @@ pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
     pub buffer: Vec<u32>,
+    // Dithering ordenado al codificar a sRGB de 8 bits
+    pub dither: bool,
     background_color: Color,
     current_color: Color,
 }
@@ -17,6 +20,7 @@ impl Framebuffer {
             width,
             height,
             buffer,
+            dither: false,
             background_color: Color::new(179, 179, 179), 
             current_color: Color::new(255, 255, 255), // Color blanco
         }
@@ -53,9 +57,18 @@ impl Framebuffer {
         self.u32_to_color(color_u32)
     }
 
+    // Guarda el contenido del buffer como imagen (el formato sale de la extensión)
+    pub fn save(&self, path: &str) -> ImageResult<()> {
+        let image = RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let color = self.get_pixel(x as usize, y as usize);
+            Rgb([color.r, color.g, color.b])
+        });
+        image.save(path)
+    }
+
     // Función para convertir un Color a un valor u32
     fn color_to_u32(&self, color: &Color) -> u32 {
-        ((255 as u32) << 24) | ((color.r as u32) << 16) | ((color.g as u32) << 8) | (color.b as u32)
+        (255_u32 << 24) | ((color.r as u32) << 16) | ((color.g as u32) << 8) | (color.b as u32)
     }
 
     // Función para convertir un valor u32 a un Color
@@ -0,0 +1,84 @@
+use nalgebra_glm::{Mat3, Mat4, Vec3};
+
+use crate::aabb::Aabb;
+use crate::ray_intersect::{Intersect, RayIntersect};
+
+// Geometría colocada con una matriz de transformación (traslación, rotación,
+// escala no uniforme). Los rayos se llevan al espacio del objeto y la
+// intersección vuelve al mundo; la dirección no se normaliza, así la distancia
+// del objeto es la misma que la del mundo.
+pub struct Instance<T> {
+    object: T,
+    to_world: Mat4,
+    to_object: Mat4,
+    // Inversa transpuesta, para llevar las normales al mundo
+    normal_matrix: Mat3,
+    // Escala media, para pasar la densidad de texels del objeto al mundo
+    scale: f32,
+    bounds: Aabb,
+}
+
+impl<T: RayIntersect> Instance<T> {
+    // None si la matriz no es invertible (por ejemplo, escala 0 en algún eje)
+    pub fn new(object: T, to_world: Mat4) -> Option<Instance<T>> {
+        let to_object = to_world.try_inverse()?;
+        let normal_matrix = to_object.fixed_view::<3, 3>(0, 0).transpose();
+        let scale = to_world.fixed_view::<3, 3>(0, 0).determinant().abs().cbrt();
+
+        // Caja del mundo que contiene las 8 esquinas transformadas
+        let local = object.bounds();
+        let mut bounds = Aabb::empty();
+        for corner in 0..8 {
+            let point = Vec3::new(
+                if corner & 1 == 0 { local.min.x } else { local.max.x },
+                if corner & 2 == 0 { local.min.y } else { local.max.y },
+                if corner & 4 == 0 { local.min.z } else { local.max.z },
+            );
+            bounds.grow(&transform_point(&to_world, &point));
+        }
+
+        Some(Instance {
+            object,
+            to_world,
+            to_object,
+            normal_matrix,
+            scale,
+            bounds,
+        })
+    }
+}
+
+impl<T: RayIntersect> RayIntersect for Instance<T> {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3, time: f32) -> Intersect {
+        let origin = transform_point(&self.to_object, ray_origin);
+        let direction = transform_vector(&self.to_object, ray_direction);
+
+        let mut hit = self.object.ray_intersect(&origin, &direction, time);
+        if hit.is_intersecting {
+            hit.point = transform_point(&self.to_world, &hit.point);
+            hit.normal = (self.normal_matrix * hit.normal).normalize();
+            hit.uv_density /= self.scale;
+            // Un cubo transformado ya no es una cara alineada de `Emitters`
+            hit.emitter = false;
+        }
+        hit
+    }
+
+    fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    fn any_hit(&self, ray_origin: &Vec3, ray_direction: &Vec3, max_distance: f32, time: f32) -> bool {
+        let origin = transform_point(&self.to_object, ray_origin);
+        let direction = transform_vector(&self.to_object, ray_direction);
+        self.object.any_hit(&origin, &direction, max_distance, time)
+    }
+}
+
+fn transform_point(matrix: &Mat4, point: &Vec3) -> Vec3 {
+    (matrix * point.push(1.0)).xyz()
+}
+
+fn transform_vector(matrix: &Mat4, vector: &Vec3) -> Vec3 {
+    (matrix * vector.push(0.0)).xyz()
+}
@@ -1,5 +1,93 @@
+use std::collections::BTreeMap;
+
 use nalgebra_glm::Vec3;
 use crate::color::Color;
+use crate::cube::Cube;
+use crate::radiance::Radiance;
+use crate::sampling;
+
+// Forma de la fuente de luz, centrada en `Light::position`. Las luces con
+// superficie se muestrean con varios rayos de sombra y dan penumbras.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightShape {
+    Point,
+    Sphere { radius: f32 },
+    // Rectángulo con lados `u` y `v`
+    Rectangle { u: Vec3, v: Vec3 },
+    // Caja de un bloque luminoso (glowstone, shroomlight), por sus semiejes
+    Block { half_extents: Vec3 },
+}
+
+// Tipo de fuente. Las puntuales y los focos salen de `Light::position`; la
+// direccional (el sol) llega desde el infinito con rayos paralelos.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    Point,
+    // `direction` es hacia donde viaja la luz. `angular_radius` (radianes) es
+    // el tamaño aparente del disco: con más de 0 las sombras son suaves.
+    Directional { direction: Vec3, angular_radius: f32 },
+    // Cono alrededor de `direction` con semiángulo `angle` (radianes); la
+    // fracción `falloff` del borde se desvanece suavemente
+    Spot { direction: Vec3, angle: f32, falloff: f32 },
+}
+
+// Cómo cae la intensidad con la distancia al centro de la luz
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum Attenuation {
+    // Igual a cualquier distancia
+    #[default]
+    None,
+    // 1/d², con intensidad completa a `reference` unidades
+    InverseSquare { reference: f32 },
+    // Como el nivel de luz de Minecraft: baja en línea recta hasta 0 a `range`
+    // unidades (15 bloques)
+    Linear { range: f32 },
+    // Curva propia: puntos (distancia, factor) ordenados por distancia,
+    // interpolados en línea recta y constantes fuera de sus extremos
+    Curve(Vec<(f32, f32)>),
+}
+
+impl Attenuation {
+    pub fn factor(&self, distance: f32) -> f32 {
+        match self {
+            Attenuation::None => 1.0,
+            Attenuation::InverseSquare { reference } => {
+                // Se limita a 100 veces: pegado a la luz el factor se dispararía
+                let ratio = reference / distance.max(reference * 0.1);
+                ratio * ratio
+            }
+            Attenuation::Linear { range } => (1.0 - distance / range).max(0.0),
+            Attenuation::Curve(points) => {
+                let Some(&(first_distance, first_factor)) = points.first() else {
+                    return 1.0;
+                };
+                if distance <= first_distance {
+                    return first_factor;
+                }
+                for pair in points.windows(2) {
+                    let ((d0, f0), (d1, f1)) = (pair[0], pair[1]);
+                    if distance <= d1 {
+                        let t = if d1 > d0 { (distance - d0) / (d1 - d0) } else { 1.0 };
+                        return f0 + (f1 - f0) * t;
+                    }
+                }
+                points[points.len() - 1].1
+            }
+        }
+    }
+
+    // Distancia desde la cual el factor es 0 para siempre, si la hay
+    pub fn range(&self) -> Option<f32> {
+        match self {
+            Attenuation::Linear { range } => Some(*range),
+            Attenuation::Curve(points) => match points.last() {
+                Some(&(distance, factor)) if factor <= 0.0 => Some(distance),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Light {
@@ -7,6 +95,16 @@ pub struct Light {
     pub color: Color,
     pub intensity: f32,
     pub day_angle: f32,  // Ángulo que representa el progreso del ciclo del día
+    pub kind: LightKind,
+    pub shape: LightShape,
+    // Rayos de sombra por punto sombreado (las luces puntuales usan uno)
+    pub samples: u32,
+    pub attenuation: Attenuation,
+    // Más allá de esta distancia la luz no aporta y no se lanzan sus rayos de sombra
+    pub range: Option<f32>,
+    // Generada desde bloques emisivos: el trazado de caminos ya recibe esa
+    // luz de los propios bloques y la ignora
+    pub from_blocks: bool,
 }
 
 impl Light {
@@ -16,6 +114,149 @@ impl Light {
             color,
             intensity,
             day_angle: 0.0,  // Iniciar el ángulo en el amanecer
+            kind: LightKind::Point,
+            shape: LightShape::Point,
+            samples: 1,
+            attenuation: Attenuation::None,
+            range: None,
+            from_blocks: false,
+        }
+    }
+
+    pub fn with_kind(mut self, kind: LightKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn with_shape(mut self, shape: LightShape, samples: u32) -> Self {
+        self.shape = shape;
+        self.samples = samples.max(1);
+        self
+    }
+
+    pub fn with_attenuation(mut self, attenuation: Attenuation, range: Option<f32>) -> Self {
+        self.attenuation = attenuation;
+        self.range = range;
+        self
+    }
+
+    // Dirección hacia el centro de la luz desde `point` y distancia hasta él
+    // (infinita para la luz direccional)
+    pub fn incidence(&self, point: &Vec3) -> (Vec3, f32) {
+        match self.kind {
+            LightKind::Directional { direction, .. } => (-direction, f32::INFINITY),
+            _ => {
+                let to_light = self.position - point;
+                (to_light.normalize(), to_light.magnitude())
+            }
+        }
+    }
+
+    // Fracción de la intensidad que llega a `point`: la atenuación por
+    // distancia y el cono de los focos. 0 fuera del alcance.
+    pub fn falloff(&self, point: &Vec3) -> f32 {
+        let (light_dir, distance) = self.incidence(point);
+        let cone = match self.kind {
+            // El sol no se atenúa con la distancia
+            LightKind::Directional { .. } => return 1.0,
+            LightKind::Point => 1.0,
+            LightKind::Spot { direction, angle, falloff } => {
+                let cosine = (-light_dir).dot(&direction);
+                let outer = angle.cos();
+                let inner = (angle * (1.0 - falloff)).cos();
+                if cosine <= outer {
+                    return 0.0;
+                }
+                if inner > outer {
+                    let t = ((cosine - outer) / (inner - outer)).clamp(0.0, 1.0);
+                    t * t * (3.0 - 2.0 * t)
+                } else {
+                    1.0
+                }
+            }
+        };
+        cone * self.attenuation_at(distance)
+    }
+
+    // Factor de la intensidad a `distance` del centro; 0 fuera del alcance
+    fn attenuation_at(&self, distance: f32) -> f32 {
+        let cutoff = match (self.range, self.attenuation.range()) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        if cutoff.is_some_and(|cutoff| distance >= cutoff) {
+            return 0.0;
+        }
+        self.attenuation.factor(distance)
+    }
+
+    pub fn sample_count(&self) -> u32 {
+        match (self.kind, self.shape) {
+            (LightKind::Directional { angular_radius, .. }, _) if angular_radius > 0.0 => self.samples,
+            (LightKind::Directional { .. }, _) | (_, LightShape::Point) => 1,
+            _ => self.samples,
+        }
+    }
+
+    // Rayo de sombra `index` desde `from`: dirección hacia la luz y distancia
+    // hasta ella. Para el sol las direcciones se reparten dentro de su disco
+    // aparente y el rayo no tiene fin.
+    pub fn shadow_ray(&self, from: &Vec3, index: u32) -> (Vec3, f32) {
+        match self.kind {
+            LightKind::Directional { direction, angular_radius } => {
+                if self.sample_count() == 1 {
+                    return (-direction, f32::INFINITY);
+                }
+                let (s, t) = sampling::stratified(index, self.sample_count(), sampling::jitter(from, index));
+                let offset = disk_offset(&-direction, angular_radius.tan(), s, t);
+                ((-direction + offset).normalize(), f32::INFINITY)
+            }
+            _ => {
+                let to_target = self.sample_point(from, index) - from;
+                (to_target.normalize(), to_target.magnitude())
+            }
+        }
+    }
+
+    // Punto de la superficie de la luz para el rayo de sombra `index` desde
+    // `from`. Las muestras se estratifican sobre la parte visible de la luz y
+    // se desplazan con ruido fijo por punto, así no aparecen bandas.
+    fn sample_point(&self, from: &Vec3, index: u32) -> Vec3 {
+        let count = self.sample_count();
+        let jitter = sampling::jitter(from, index);
+        match self.shape {
+            LightShape::Point => self.position,
+            LightShape::Sphere { radius } => {
+                // Disco de la esfera visto desde `from`: su silueta
+                let to_from = from - self.position;
+                if to_from.magnitude() <= radius {
+                    return self.position;
+                }
+                let (s, t) = sampling::stratified(index, count, jitter);
+                self.position + disk_offset(&to_from.normalize(), radius, s, t)
+            }
+            LightShape::Rectangle { u, v } => {
+                let (s, t) = sampling::stratified(index, count, jitter);
+                self.position + u * (s - 0.5) + v * (t - 0.5)
+            }
+            LightShape::Block { half_extents } => {
+                // Solo las caras que miran hacia `from`; las muestras se reparten entre ellas
+                let local = from - self.position;
+                let faces: Vec<usize> = (0..3).filter(|&axis| local[axis].abs() > half_extents[axis]).collect();
+                if faces.is_empty() {
+                    return self.position;
+                }
+                let face_count = faces.len() as u32;
+                let axis = faces[(index % face_count) as usize];
+                let per_face = count.div_ceil(face_count);
+                let (s, t) = sampling::stratified(index / face_count, per_face, jitter);
+                let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+                let mut offset = Vec3::zeros();
+                offset[axis] = half_extents[axis].copysign(local[axis]);
+                offset[a] = (2.0 * s - 1.0) * half_extents[a];
+                offset[b] = (2.0 * t - 1.0) * half_extents[b];
+                self.position + offset
+            }
         }
     }
 
@@ -27,6 +268,11 @@ impl Light {
             radius * self.day_angle.sin(),  // Movimiento vertical (Y)
             6.0
         );
+        // Como luz direccional, el sol llega desde esa posición vista desde el
+        // origen, con rayos paralelos en toda la escena
+        if let LightKind::Directional { direction, .. } = &mut self.kind {
+            *direction = -self.position.normalize();
+        }
     
         // Condiciones para el amanecer/atardecer (cuando el sol está cerca del horizonte)
         let sunrise_threshold = 0.5;  // Cercanía al horizonte por la mañana (0 radianes)
@@ -55,3 +301,148 @@ impl Light {
         self.day_angle = (self.day_angle + delta_angle) % (2.0 * std::f32::consts::PI);
     }
 }
+
+// Punto de un disco de radio `radius` perpendicular a `normal`, para el par
+// (s, t) en [0, 1)²
+fn disk_offset(normal: &Vec3, radius: f32, s: f32, t: f32) -> Vec3 {
+    let helper = if normal.x.abs() < 0.9 { Vec3::x() } else { Vec3::y() };
+    let u = normal.cross(&helper).normalize();
+    let v = normal.cross(&u);
+    let r = radius * s.sqrt();
+    let angle = 2.0 * std::f32::consts::PI * t;
+    (u * angle.cos() + v * angle.sin()) * r
+}
+
+// Genera luces a partir de los cubos emisivos. Los bloques se agrupan en
+// celdas de `cluster_size` para no crear una luz por bloque; cada grupo da una
+// luz con la emisión promedio. Sin `block_samples` es una luz puntual medio
+// bloque por encima de las caras superiores (dentro del bloque la luz quedaría
+// tapada por el propio cubo, y pegada a la cara no alcanzaría a iluminar el
+// suelo alrededor). Con `block_samples` la luz es la caja que ocupa el grupo,
+// muestreada sobre sus caras, y da sombras suaves.
+pub fn emissive_lights(cubes: &[Cube], cluster_size: f32, intensity: f32, block_samples: Option<u32>) -> Vec<Light> {
+    struct Cluster {
+        top: Vec3,
+        min: Vec3,
+        max: Vec3,
+        emission: Radiance,
+        count: usize,
+    }
+    let mut clusters: BTreeMap<(i32, i32, i32), Cluster> = BTreeMap::new();
+
+    for cube in cubes.iter().filter(|cube| cube.material.is_emissive()) {
+        let center = (cube.min + cube.max) * 0.5;
+        let key = (
+            (center.x / cluster_size).floor() as i32,
+            (center.y / cluster_size).floor() as i32,
+            (center.z / cluster_size).floor() as i32,
+        );
+        let top = Vec3::new(center.x, cube.max.y + (cube.max.y - cube.min.y) * 0.5, center.z);
+
+        let cluster = clusters.entry(key).or_insert(Cluster {
+            top: Vec3::zeros(),
+            min: cube.min,
+            max: cube.max,
+            emission: Radiance::black(),
+            count: 0,
+        });
+        cluster.top += top;
+        cluster.min = cluster.min.inf(&cube.min);
+        cluster.max = cluster.max.sup(&cube.max);
+        cluster.emission += cube.material.average_emission();
+        cluster.count += 1;
+    }
+
+    clusters
+        .into_values()
+        .filter_map(|cluster| {
+            let emission = cluster.emission * (1.0 / cluster.count as f32);
+            // El color se normaliza a su canal más alto y el resto va a la intensidad
+            let peak = emission.r.max(emission.g).max(emission.b);
+            if peak <= 0.0 {
+                return None;
+            }
+            let color = (emission * (1.0 / peak)).to_srgb(0.0);
+            let mut light = match block_samples {
+                Some(samples) => {
+                    let shape = LightShape::Block { half_extents: (cluster.max - cluster.min) * 0.5 };
+                    Light::new((cluster.min + cluster.max) * 0.5, color, peak * intensity).with_shape(shape, samples)
+                }
+                None => Light::new(cluster.top / cluster.count as f32, color, peak * intensity),
+            };
+            light.from_blocks = true;
+            Some(light)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-5, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn inverse_square_is_full_at_the_reference_and_capped_near_the_light() {
+        let attenuation = Attenuation::InverseSquare { reference: 2.0 };
+        assert_close(attenuation.factor(2.0), 1.0);
+        assert_close(attenuation.factor(4.0), 0.25);
+        assert_close(attenuation.factor(20.0), 0.01);
+        // Más cerca que una décima de `reference` ya no crece
+        assert_close(attenuation.factor(0.2), 100.0);
+        assert_close(attenuation.factor(0.0), 100.0);
+        assert_eq!(attenuation.range(), None);
+    }
+
+    #[test]
+    fn linear_reaches_zero_at_its_range() {
+        let attenuation = Attenuation::Linear { range: 7.5 };
+        assert_close(attenuation.factor(0.0), 1.0);
+        assert_close(attenuation.factor(3.75), 0.5);
+        assert_close(attenuation.factor(7.5), 0.0);
+        assert_close(attenuation.factor(100.0), 0.0);
+        assert_eq!(attenuation.range(), Some(7.5));
+    }
+
+    #[test]
+    fn curves_interpolate_and_hold_their_ends() {
+        let attenuation = Attenuation::Curve(vec![(1.0, 0.8), (3.0, 0.4), (3.0, 0.2), (5.0, 0.0)]);
+        assert_close(attenuation.factor(0.0), 0.8);
+        assert_close(attenuation.factor(1.0), 0.8);
+        assert_close(attenuation.factor(2.0), 0.6);
+        // Dos puntos a la misma distancia forman un escalón
+        assert_close(attenuation.factor(3.0), 0.4);
+        assert_close(attenuation.factor(3.5), 0.15);
+        assert_close(attenuation.factor(9.0), 0.0);
+        assert_eq!(attenuation.range(), Some(5.0));
+
+        // Sin llegar a 0 no tiene alcance, y sin puntos no atenúa
+        assert_eq!(Attenuation::Curve(vec![(1.0, 1.0), (4.0, 0.5)]).range(), None);
+        assert_close(Attenuation::Curve(vec![(1.0, 1.0), (4.0, 0.5)]).factor(10.0), 0.5);
+        assert_close(Attenuation::Curve(Vec::new()).factor(3.0), 1.0);
+    }
+
+    #[test]
+    fn range_cuts_off_at_the_nearest_limit() {
+        let light = Light::new(Vec3::zeros(), Color::new(255, 255, 255), 1.0);
+        assert_close(light.attenuation_at(1e6), 1.0);
+
+        let light = light.with_attenuation(Attenuation::InverseSquare { reference: 1.0 }, Some(4.0));
+        assert_close(light.attenuation_at(2.0), 0.25);
+        assert_close(light.attenuation_at(4.0), 0.0);
+
+        // El alcance propio de la atenuación gana si es menor, y al revés
+        let light = light.with_attenuation(Attenuation::Linear { range: 2.0 }, Some(4.0));
+        assert_close(light.attenuation_at(1.0), 0.5);
+        assert_close(light.attenuation_at(2.0), 0.0);
+        let light = light.with_attenuation(Attenuation::Linear { range: 8.0 }, Some(4.0));
+        assert_close(light.attenuation_at(2.0), 0.75);
+        assert_close(light.attenuation_at(4.0), 0.0);
+
+        // `falloff` aplica la misma atenuación según la posición
+        assert_close(light.falloff(&Vec3::new(0.0, 2.0, 0.0)), 0.75);
+        assert_close(light.falloff(&Vec3::new(0.0, 0.0, -5.0)), 0.0);
+    }
+}
@@ -1,10 +1,7 @@
-use material::{Material, TextureType};
-use minifb::{Key, Window, WindowOptions};
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
 use nalgebra_glm::Vec3;
-use std::{f32::consts::PI, time::Duration};
+use std::{f32::consts::PI, time::{Duration, Instant}};
 use rayon::prelude::*;
-use once_cell::sync::Lazy;
-use std::sync::Arc;
 
 
 mod framebuffer;
@@ -16,267 +13,265 @@ use color::Color;
 mod ray_intersect;
 use ray_intersect::Intersect;
 
+mod aabb;
+
+mod bvh;
+use bvh::Bvh;
+
+mod voxel;
+
+mod nbt;
+
+mod structure;
+
+mod anvil;
+
+mod resource_pack;
+
+mod shape;
+
+mod cli;
+use cli::Options;
+
 mod material;
+use material::ShadingModel;
+
+mod brdf;
 
 mod camera;
 use camera::Camera;
 
 mod cube;
-use cube::Cube;
+
+mod instance;
+
+mod mesh;
+
+mod obj;
 
 mod light;
 use light::Light;
 
+mod sampling;
+use sampling::Rng;
+
+mod radiance;
+
+mod tonemap;
+use tonemap::ToneMapping;
+
+mod srgb;
+
 mod castray;
-use castray::cast_ray;
+use castray::{cast_ray, RenderContext};
+
+mod pathtrace;
+use pathtrace::{trace_path, Accumulator, Integrator};
 
 mod texture;
-use texture::Texture;
 
+mod sampler;
 
+mod texture_registry;
 
-pub fn render(framebuffer: &mut Framebuffer, objects: &[Cube], camera: &Camera, light: &[Light], daylight: &Light) {
-    let width = framebuffer.width as f32;
-    let height = framebuffer.height as f32;
+mod scene;
+use scene::Scene;
+
+
+
+// Dirección del rayo de la cámara por el punto (x, y) de la imagen, en pixeles
+fn primary_ray(camera: &Camera, x: f32, y: f32, width: f32, height: f32) -> Vec3 {
     let aspect_ratio = width / height;
     let fov = PI / 3.0;
     let perspective_scale = (fov / 2.0).tan();
 
-    framebuffer.buffer.par_chunks_mut(framebuffer.width as usize).enumerate().for_each(|(y, row)| {
-        let screen_y = -(2.0 * y as f32) / height + 1.0;
-        let screen_y = screen_y * perspective_scale;
+    let screen_x = (2.0 * x) / width - 1.0;
+    let screen_x = screen_x * aspect_ratio * perspective_scale;
+    let screen_y = -(2.0 * y) / height + 1.0;
+    let screen_y = screen_y * perspective_scale;
 
-        row.iter_mut().enumerate().for_each(|(x, pixel)| {
-            let screen_x = (2.0 * x as f32) / width - 1.0;
-            let screen_x = screen_x * aspect_ratio * perspective_scale;
+    let ray_direction = Vec3::new(screen_x, screen_y, -1.0).normalize();
+    camera.basis_change(&ray_direction)
+}
+
+// Apertura de un pixel en radianes para una imagen de `height` pixeles
+fn pixel_spread(height: f32) -> f32 {
+    2.0 * (PI / 6.0).tan() / height
+}
 
-            let ray_direction = Vec3::new(screen_x, screen_y, -1.0).normalize();
-            let rotated_direction = camera.basis_change(&ray_direction);
+// `context` trae la escena y el estado del cuadro; la apertura del pixel se
+// calcula aquí a partir del tamaño de la imagen
+pub fn render(framebuffer: &mut Framebuffer, camera: &Camera, context: &RenderContext, tone_mapping: &ToneMapping) {
+    let width = framebuffer.width as f32;
+    let height = framebuffer.height as f32;
+    let dither = framebuffer.dither;
+    let context = RenderContext {
+        pixel_spread: pixel_spread(height),
+        ..*context
+    };
+
+    framebuffer.buffer.par_chunks_mut(framebuffer.width).enumerate().for_each(|(y, row)| {
+        row.iter_mut().enumerate().for_each(|(x, pixel)| {
+            let rotated_direction = primary_ray(camera, x as f32, y as f32, width, height);
 
-            let pixel_color = cast_ray(&camera.eye, &rotated_direction, objects, daylight, light, 0);
-            *pixel = pixel_color.to_u32();
+            let pixel_color = cast_ray(&camera.eye, &rotated_direction, &context, 0, 0.0);
+            let dither_offset = if dither { srgb::dither_offset(x, y) } else { 0.0 };
+            *pixel = tone_mapping.map(pixel_color).to_srgb(dither_offset).to_u32();
         });
     });
 }
 
+// Una pasada del trazado de caminos: suma un camino por pixel, con un punto
+// al azar dentro del pixel, y muestra el promedio de todas las pasadas
+pub fn render_path(
+    framebuffer: &mut Framebuffer,
+    camera: &Camera,
+    context: &RenderContext,
+    accumulator: &mut Accumulator,
+    tone_mapping: &ToneMapping,
+) {
+    let width = framebuffer.width as f32;
+    let height = framebuffer.height as f32;
+    let dither = framebuffer.dither;
+    let context = RenderContext {
+        pixel_spread: pixel_spread(height),
+        ..*context
+    };
+    accumulator.samples += 1;
+    let pass = accumulator.samples;
+    let scale = 1.0 / pass as f32;
+
+    framebuffer
+        .buffer
+        .par_chunks_mut(framebuffer.width)
+        .zip(accumulator.sum.par_chunks_mut(framebuffer.width))
+        .enumerate()
+        .for_each(|(y, (row, sums))| {
+            row.iter_mut().zip(sums).enumerate().for_each(|(x, (pixel, sum))| {
+                let index = (y * framebuffer.width + x) as u32;
+                let mut rng = Rng::new(index ^ sampling::hash(pass));
+                let (jx, jy) = (rng.next_f32() - 0.5, rng.next_f32() - 0.5);
+                let direction = primary_ray(camera, x as f32 + jx, y as f32 + jy, width, height);
+
+                let sample = trace_path(&camera.eye, &direction, &context, &mut rng);
+                // Un camino con NaN arruinaría el pixel para siempre
+                if sample.r.is_finite() && sample.g.is_finite() && sample.b.is_finite() {
+                    *sum += sample;
+                }
+                let dither_offset = if dither { srgb::dither_offset(x, y) } else { 0.0 };
+                *pixel = tone_mapping.map(*sum * scale).to_srgb(dither_offset).to_u32();
+            });
+        });
+}
+
 fn main() {
-    let width = 1000;
-    let height = 550;
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+
+    let width = options.width;
+    let height = options.height;
 
     let mut framebuffer = Framebuffer::new(width, height);
+    framebuffer.dither = options.dither;
 
     let frame_delay = Duration::from_millis(0);
 
-   
-    let mut window = Window::new(
-        "Raytracing",
-        width,
-        height,
-        WindowOptions::default(),
-    ).unwrap();
-
-    window.set_position(100, 100);
-    window.update();
-
-    framebuffer.set_background_color(Color::new(179, 179, 179));
-
-    
-    let netherrack = Material::new_with_texture(
-        30.0,
-        [0.6, 0.2],
-        0.0,
-        0.0,
-        0.0,
-        TextureType::Netherrack
-    );
-
-    let obsidian = Material::new_with_texture(
-        50.0,
-        [0.6, 0.5],
-        0.0,
-        0.0,
-        0.0,
-        TextureType::Obsidian
-    );
-
-    let ruinedobsidian = Material::new_with_texture(
-        40.0,
-        [0.6, 0.3],
-        0.0,
-        0.0,
-        0.0,
-        TextureType::RuinedObsidian
-    );
-
-    let magma = Material::new_with_texture(
-        100.0,
-        [0.7, 0.7],
-        0.1,
-        0.0,
-        0.0,
-        TextureType::MagmaBlock
-    );
-
-    let lava = Material::new_with_texture(
-        100.0,
-        [0.7, 0.9],
-        0.1,
-        0.2,
-        0.3,
-        TextureType::Lava
-    );
-
-    let sand = Material::new_with_texture(
-        30.0,
-        [0.8, 0.1],
-        0.0,
-        0.0,
-        0.0,
-        TextureType::Sand
-    );
-
-    let mut objects = Vec::new();
-    let lights = vec![
-        Light::new(Vec3::new(2.75, -0.4, 0.25), Color::new(255, 185, 46), 1.0),
-        Light::new(Vec3::new(2.75, -0.20, 0.1), Color::new(255, 185, 46), 1.0),
-        Light::new(Vec3::new(1.75, -0.4, 1.25), Color::new(255, 185, 46), 1.0),
-        Light::new(Vec3::new(1.75, -0.20, 1.50), Color::new(255, 185, 46), 1.0),
-        Light::new(Vec3::new(1.25, -0.4, 1.75), Color::new(255, 185, 46), 1.0),
-        Light::new(Vec3::new(1.25, -0.20, 2.1), Color::new(255, 185, 46), 1.0),
-    ];
-    let cube_size = 0.5;  // Tamaño del cubo
-
-    for i in 0..6 { // Número de cubos en la dirección x 
-        for j in 0..4 { // Número de cubos en la dirección z 
-            let material = if (i + j) % 3 == 0 {
-                magma.clone() // Algunas partes del piso serán magma
-            } else if (i + j) % 5 == 0 {
-                lava.clone() // Algunas partes del piso serán lava
-            } else {
-                netherrack.clone() // El resto del piso será netherrack
-            };
-            let max = if (i + j) % 3 == 0 {
-                Vec3::new(i as f32 * cube_size + cube_size, -0.5, j as f32 * cube_size + cube_size) // Algunas partes del piso serán magma
-            } else if (i + j) % 5 == 0 {
-                Vec3::new(i as f32 * cube_size + cube_size, -0.55, j as f32 * cube_size + cube_size) // Algunas partes del piso serán lava
-            } else {
-                Vec3::new(i as f32 * cube_size + cube_size, -0.5, j as f32 * cube_size + cube_size) // El resto del piso será netherrack
-            };
-            objects.push(Cube {
-                min: Vec3::new(i as f32 * cube_size, -1.0, j as f32 * cube_size), // Vértice inferior izquierdo
-                max, // Vértice superior derecho
-                material,
-            });
+    let Scene { objects, lights, mut daylight, emitters, mut camera, imports } = match Scene::load(&options.scene) {
+        Ok(scene) => scene,
+        Err(e) => {
+            eprintln!("Error loading scene: {}", e);
+            std::process::exit(1);
         }
-    }
-
-    for i in 0..7 { // Número de cubos en la dirección x 
-        for j in 0..1 { // Número de cubos en la dirección z 
-            objects.push(Cube {
-                min: Vec3::new(i as f32 * cube_size, -1.5, (j + 4) as f32 * cube_size), // Vértice inferior izquierdo
-                max: Vec3::new(i as f32 * cube_size + cube_size, -1.0, (j + 4) as f32 * cube_size + cube_size), // Vértice superior derecho
-                material: sand.clone(),
-            });
+    };
+    // Sin ventana la salida estándar queda solo para el archivo guardado
+    for (path, report) in &imports {
+        if options.headless {
+            eprintln!("{}: {}", path, report);
+        } else {
+            println!("{}: {}", path, report);
         }
     }
 
-    for i in 0..1 { // Número de cubos en la dirección x 
-        for j in 0..4 { // Número de cubos en la dirección z 
-            objects.push(Cube {
-                min: Vec3::new((i + 6) as f32 * cube_size, -1.5, j as f32 * cube_size), // Vértice inferior izquierdo
-                max: Vec3::new((i + 6) as f32 * cube_size + cube_size, -1.0, j as f32 * cube_size + cube_size), // Vértice superior derecho
-                material: sand.clone(),
-            });
-        }
+    if let Some(eye) = options.eye {
+        camera.eye = eye;
     }
-
-    for i in 0..3 { // Número de cubos en la dirección x 
-        for j in 0..1 { // Número de cubos en la dirección z 
-            objects.push(Cube {
-                min: Vec3::new((i + 1) as f32 * cube_size, -1.0, (j+4) as f32 * cube_size), // Vértice inferior izquierdo
-                max: Vec3::new((i + 1) as f32 * cube_size + cube_size, -0.5, (j+4) as f32 * cube_size + cube_size), // Vértice superior derecho
-                material: netherrack.clone(),
-            });
-        }
+    if let Some(center) = options.center {
+        camera.center = center;
     }
-
-    for i in 0..1 { // Número de cubos en la dirección x 
-        for j in 0..2 { // Número de cubos en la dirección z 
-            objects.push(Cube {
-                min: Vec3::new((i + 6) as f32 * cube_size, -1.0, j as f32 * cube_size), // Vértice inferior izquierdo
-                max: Vec3::new((i + 6) as f32 * cube_size + cube_size, -0.5, j as f32 * cube_size + cube_size), // Vértice superior derecho
-                material: netherrack.clone(),
-            });
-        }
+    if let Some(day_angle) = options.day_angle {
+        daylight.day_angle = day_angle;
     }
 
-    for i in 0..4 {  // Número de cubos en la dirección x 
-        for j in 0..1 {  // Número de cubos en la dirección z 
-            let material = if i == 2 {  // Si es el tercer cubo (i == 2), usa ruinedobsidian
-                ruinedobsidian.clone()
-            } else {
-                obsidian.clone()
-            };
-    
-            objects.push(Cube {
-                min: Vec3::new((i + 1) as f32 * cube_size, -0.5, (j + 1) as f32 * cube_size),  // Vértice inferior izquierdo
-                max: Vec3::new((i + 1) as f32 * cube_size + cube_size, 0.0, (j + 1) as f32 * cube_size + cube_size),  // Vértice superior derecho
-                material,  // Material del cubo
-            });
-        }
-    }
+    // Estructura de aceleración sobre todos los objetos de la escena
+    let objects = Bvh::new(objects);
 
-    for k in 0..4 {  // 4 bloques hacia arriba 
-        let y_min = 0.0 + k as f32 * cube_size;  // Coordenada inferior en y
-        let y_max = y_min + cube_size;  // Coordenada superior en y
-    
-        let material = if k == 2 || k == 3 {  // Si es el tercer o cuarto cubo (k == 2 o k == 3), usa ruinedobsidian
-            ruinedobsidian.clone()
-        } else {
-            obsidian.clone()
-        };
-    
-        objects.push(Cube {
-            min: Vec3::new(0.5, y_min, 0.5),  // Vértice inferior izquierdo
-            max: Vec3::new(0.5 + cube_size, y_max, 0.5 + cube_size),  // Vértice superior derecho
-            material,  // Material del cubo
-        });
-    }
+    framebuffer.set_background_color(Color::new(179, 179, 179));
 
-    for k in 0..3 {  // 3 bloques hacia arriba
-        let y_min = 0.0 + k as f32 * cube_size;  // Coordenada inferior en y
-        let y_max = y_min + cube_size;  // Coordenada superior en y
-    
-        let material = if k == 1 {  // Si es el segundo cubo (k == 1), usa ruinedobsidian
-            ruinedobsidian.clone()
-        } else {
-            obsidian.clone()
+    let mut tone_mapping = ToneMapping {
+        operator: options.tonemap,
+        exposure: options.exposure,
+    };
+    let mut shading = options.shading;
+    let mut integrator = options.integrator;
+    let mut accumulator = Accumulator::new(width * height);
+
+    // Sin ventana: un solo cuadro directo a un archivo
+    if options.headless {
+        daylight.update_light();
+        framebuffer.clear();
+        let context = RenderContext {
+            objects: &objects,
+            daylight: &daylight,
+            lights: &lights,
+            emitters: &emitters,
+            time: options.time,
+            pixel_spread: 0.0,
+            shading,
         };
-    
-        objects.push(Cube {
-            min: Vec3::new(2.0, y_min, 0.5),  // Vértice inferior izquierdo
-            max: Vec3::new(2.0 + cube_size, y_max, 0.5 + cube_size),  // Vértice superior derecho
-            material,  // Material del cubo
-        });
+        match integrator {
+            Integrator::Whitted => render(&mut framebuffer, &camera, &context, &tone_mapping),
+            Integrator::Path => {
+                for _ in 0..options.samples {
+                    render_path(&mut framebuffer, &camera, &context, &mut accumulator, &tone_mapping);
+                }
+            }
+        }
+
+        if let Err(e) = framebuffer.save(&options.output) {
+            eprintln!("Error saving {}: {}", options.output, e);
+            std::process::exit(1);
+        }
+        println!("Saved {}", options.output);
+        return;
     }
 
-    let mut camera = Camera::new(
-        Vec3::new(2.0,2.0,7.0),
-        Vec3::new(1.5,1.0,0.0),
-        Vec3::new(0.0,1.0,0.0), 
-    );
+    let mut window = Window::new(
+        "Raytracing",
+        width,
+        height,
+        WindowOptions::default(),
+    ).unwrap();
+
+    window.set_position(100, 100);
+    window.update();
 
-    let mut daylight = Light::new(
-        Vec3::new(4.0, 3.0, 7.0), 
-        Color::new(229, 156, 19), 
-        2.0
-    );
-    
-    
+    // Reloj de las texturas animadas, que avanza en tiempo real
+    let start = Instant::now();
 
     let rotaton_speed = PI/50.0;
     let zoom_speed = 0.1; 
 
+    let mut last_view = None;
+
     // Bucle principal
     while window.is_open() {
         if window.is_key_down(Key::Escape) {
@@ -312,17 +307,63 @@ fn main() {
            daylight.advance_day_cycle(-day_speed);  // Retrocede el ciclo hacia el amanecer
         }
 
+        // Mapeo de tonos: T cambia de operador, +/- ajustan la exposición
+        if window.is_key_pressed(Key::T, KeyRepeat::No) {
+            tone_mapping.operator = tone_mapping.operator.next();
+            println!("Tone mapping: {}", tone_mapping.operator.name());
+        }
+        if window.is_key_down(Key::Equal) {
+            tone_mapping.exposure += 0.1;
+        }
+        if window.is_key_down(Key::Minus) {
+            tone_mapping.exposure -= 0.1;
+        }
+
+        // M fuerza Phong o PBR en todos los materiales, o vuelve al de cada uno
+        if window.is_key_pressed(Key::M, KeyRepeat::No) {
+            shading = match shading {
+                None => Some(ShadingModel::Phong),
+                Some(ShadingModel::Phong) => Some(ShadingModel::Pbr),
+                Some(ShadingModel::Pbr) => None,
+            };
+            println!("Shading: {}", shading.map_or("per material", ShadingModel::name));
+        }
+
+        // I alterna entre el trazado de rayos y el de caminos
+        if window.is_key_pressed(Key::I, KeyRepeat::No) {
+            integrator = integrator.next();
+            println!("Integrator: {}", integrator.name());
+        }
+
         daylight.update_light();
 
         framebuffer.clear();
 
-        render(
-            &mut framebuffer,
-            &objects,
-            &mut camera,
-            &lights,
-            &daylight
-        );
+        // Las pasadas del trazado de caminos se acumulan mientras la vista no
+        // cambie; el mapeo de tonos se aplica al promedio y no la reinicia
+        let view = (camera.eye, camera.center, daylight.day_angle, shading, integrator);
+        if last_view != Some(view) {
+            accumulator.reset();
+            last_view = Some(view);
+        }
+
+        let context = RenderContext {
+            objects: &objects,
+            daylight: &daylight,
+            lights: &lights,
+            emitters: &emitters,
+            time: options.time + start.elapsed().as_secs_f32(),
+            pixel_spread: 0.0,
+            shading,
+        };
+        match integrator {
+            Integrator::Whitted => render(&mut framebuffer, &camera, &context, &tone_mapping),
+            // Las texturas animadas se detienen para que las pasadas coincidan
+            Integrator::Path => {
+                let context = RenderContext { time: options.time, ..context };
+                render_path(&mut framebuffer, &camera, &context, &mut accumulator, &tone_mapping);
+            }
+        }
 
         window
             .update_with_buffer(&framebuffer.buffer, width, height)
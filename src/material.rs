@@ -1,32 +1,120 @@
-use once_cell::sync::Lazy;
 use std::sync::Arc;
 
+use nalgebra_glm::Vec3;
+
 use crate::color::Color;
-use crate::texture::Texture;
-
-// Static texture initialization
-static TEXTURES: Lazy<Vec<Arc<Texture>>> = Lazy::new(|| {
-    vec![
-        Arc::new(Texture::new("./assets/netherrack.jpg")),
-        Arc::new(Texture::new("./assets/obsidian.png")),
-        Arc::new(Texture::new("./assets/ruinedobsidian.png")),
-        Arc::new(Texture::new("./assets/magmablock.png")),
-        Arc::new(Texture::new("./assets/lava.png")),
-        Arc::new(Texture::new("./assets/sand.png")),  
-    ]
-});
-
-#[derive(Debug, Clone)]
-pub enum TextureType {
-    Netherrack,
-    Obsidian,
-    RuinedObsidian,
-    MagmaBlock,
-    Lava,
-    Sand
+use crate::cube::{rotate_uv, Face};
+use crate::radiance::Radiance;
+use crate::sampler::Sampler;
+use crate::texture::TextureHandle;
+
+// Profundidad por defecto del relieve, en fracciones de la textura (2 pixeles de 16)
+pub const DEFAULT_RELIEF_DEPTH: f32 = 0.125;
+
+// Capas del parallax: más capas, menos escalones en relieves profundos
+const PARALLAX_STEPS: usize = 16;
+
+// Reflectancia a incidencia normal de los dieléctricos comunes (4%)
+pub const DEFAULT_F0: f32 = 0.04;
+
+// En el canal verde del `_s` de LabPBR, desde este valor el material es un metal
+const LABPBR_METAL_START: f32 = 230.0 / 255.0;
+
+// Cómo responde la superficie a las luces
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ShadingModel {
+    // Difuso y especular de Phong con `albedo` y `spec`
+    #[default]
+    Phong,
+    // Microfacetas GGX con rugosidad y metalicidad, conservando la energía
+    Pbr,
+}
+
+impl ShadingModel {
+    pub fn parse(name: &str) -> Option<ShadingModel> {
+        match name {
+            "phong" => Some(ShadingModel::Phong),
+            "pbr" => Some(ShadingModel::Pbr),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ShadingModel::Phong => "phong",
+            ShadingModel::Pbr => "pbr",
+        }
+    }
+}
+
+// Parámetros del modelo PBR en un punto de la superficie
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PbrParams {
+    pub roughness: f32,
+    pub metalness: f32,
+    pub f0: f32,
+    // Fracción del color de la superficie que emite (alfa del `_s` de LabPBR)
+    pub emission: f32,
+}
+
+// Rugosidad con un brillo parecido al de un exponente de Phong
+pub fn roughness_from_spec(spec: f32) -> f32 {
+    (2.0 / (spec.max(0.0) + 2.0)).sqrt().sqrt()
+}
+
+// Textura propia de una cara, rotada en sentido horario (0, 90, 180 o 270 grados).
+// `uv` es el recorte de la textura [u1, v1, u2, v2] en fracciones de la imagen,
+// con el origen arriba a la izquierda como en los modelos de Minecraft.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FaceTexture {
+    pub texture: TextureHandle,
+    pub rotation: u16,
+    pub uv: [f32; 4],
+}
+
+impl FaceTexture {
+    pub const FULL_UV: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+    // Pasa las coordenadas (u, v) de la cara a coordenadas de la textura
+    pub fn map_uv(&self, uv: (f32, f32)) -> (f32, f32) {
+        let (u, v) = rotate_uv(uv, self.rotation);
+        let s = self.uv[0] + (self.uv[2] - self.uv[0]) * u;
+        let t = self.uv[1] + (self.uv[3] - self.uv[1]) * (1.0 - v);
+        (s, 1.0 - t)
+    }
+
+    // La misma textura restringida a una parte de la cara: `u` y `v` son los
+    // rangos de la cara (antes de rotar, v hacia arriba) que quedan visibles
+    pub fn crop(&self, u: (f32, f32), v: (f32, f32)) -> FaceTexture {
+        // El rectángulo rotado sigue alineado a los ejes: basta con sus esquinas
+        let a = rotate_uv((u.0, v.0), self.rotation);
+        let b = rotate_uv((u.1, v.1), self.rotation);
+        let (u0, u1) = (a.0.min(b.0), a.0.max(b.0));
+        let (v0, v1) = (a.1.min(b.1), a.1.max(b.1));
+
+        let s = |u: f32| self.uv[0] + (self.uv[2] - self.uv[0]) * u;
+        let t = |v: f32| self.uv[1] + (self.uv[3] - self.uv[1]) * (1.0 - v);
+        FaceTexture {
+            texture: self.texture.clone(),
+            rotation: self.rotation,
+            uv: [s(u0), t(v1), s(u1), t(v0)],
+        }
+    }
+
+    // Direcciones de u y v de la textura a partir de las de la cara: la
+    // rotación las intercambia y un recorte invertido las refleja
+    pub fn tangents(&self, (u_axis, v_axis): (Vec3, Vec3)) -> (Vec3, Vec3) {
+        let (tangent, bitangent) = match self.rotation {
+            90 => (-v_axis, u_axis),
+            180 => (-u_axis, -v_axis),
+            270 => (v_axis, -u_axis),
+            _ => (u_axis, v_axis),
+        };
+        (tangent * (self.uv[2] - self.uv[0]).signum(), bitangent * (self.uv[3] - self.uv[1]).signum())
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Material {
     pub diffuse: Color,
     pub spec: f32,
@@ -34,8 +122,37 @@ pub struct Material {
     pub reflectivity: f32,
     pub transparency: f32,
     pub refraction_index: f32,
-    pub has_texture: bool,
-    pub texture_index: usize, // Almacena el índice de la textura
+    pub texture: Option<TextureHandle>,
+    // Texturas por cara (índice `Face::index`); las caras sin una usan `texture`.
+    // Compartidas para que clonar el material en cada intersección sea barato
+    pub faces: Option<Arc<[Option<FaceTexture>; 6]>>,
+    // Luz propia (sRGB), sumada sin importar la iluminación
+    pub emission: Color,
+    pub emission_strength: f32,
+    // Máscara opcional: el color emitido se multiplica por esta textura
+    pub emission_texture: Option<TextureHandle>,
+    // Recorte por alfa (plantas, fuego, antorchas): los rayos atraviesan los
+    // texels con opacidad menor a este valor
+    pub alpha_cutoff: Option<f32>,
+    // Filtrado y repetición de todas las texturas del material
+    pub sampler: Sampler,
+    // Mapa de normales en espacio tangente (RGB, verde hacia +v como en
+    // OpenGL). Sin él se usa el `_n` LabPBR de la textura, si lo tiene.
+    pub normal_map: Option<TextureHandle>,
+    // Alturas en escala de grises (blanco = superficie) para el parallax; sin
+    // mapa de normales, las normales salen de sus pendientes
+    pub height_map: Option<TextureHandle>,
+    // Profundidad del relieve, en fracciones de la textura
+    pub relief_depth: f32,
+    pub model: ShadingModel,
+    // Parámetros PBR: rugosidad perceptual [0, 1], metalicidad [0, 1] y
+    // reflectancia a incidencia normal de la parte no metálica
+    pub roughness: f32,
+    pub metalness: f32,
+    pub f0: f32,
+    // Mapa especular con el formato `_s` de LabPBR; sin él se usa el de la
+    // textura, si lo tiene
+    pub specular_map: Option<TextureHandle>,
 }
 
 impl Material {
@@ -55,8 +172,21 @@ impl Material {
             reflectivity,
             transparency,
             refraction_index,
-            has_texture: false,
-            texture_index: 0, // Default a 0, sin textura inicialmente
+            texture: None, // Sin textura inicialmente
+            faces: None,
+            emission: Color::black(),
+            emission_strength: 0.0,
+            emission_texture: None,
+            alpha_cutoff: None,
+            sampler: Sampler::default(),
+            normal_map: None,
+            height_map: None,
+            relief_depth: DEFAULT_RELIEF_DEPTH,
+            model: ShadingModel::Phong,
+            roughness: roughness_from_spec(spec),
+            metalness: 0.0,
+            f0: DEFAULT_F0,
+            specular_map: None,
         }
     }
 
@@ -67,17 +197,8 @@ impl Material {
         reflectivity: f32,
         transparency: f32,
         refraction_index: f32,
-        texture_type: TextureType, // Solo un tipo de textura
+        texture: TextureHandle,
     ) -> Self {
-        let texture_index = match texture_type {
-            TextureType::Netherrack => 0, 
-            TextureType::Obsidian => 1,
-            TextureType::RuinedObsidian => 2,
-            TextureType::MagmaBlock => 3,
-            TextureType::Lava => 4,
-            TextureType::Sand => 5,
-        };
-
         Material {
             diffuse: Color::new(0, 0, 0), 
             spec,
@@ -85,20 +206,261 @@ impl Material {
             reflectivity,
             transparency,
             refraction_index,
-            has_texture: true,
-            texture_index, // Guarda el índice de la textura
+            texture: Some(texture),
+            faces: None,
+            emission: Color::black(),
+            emission_strength: 0.0,
+            emission_texture: None,
+            alpha_cutoff: None,
+            sampler: Sampler::default(),
+            normal_map: None,
+            height_map: None,
+            relief_depth: DEFAULT_RELIEF_DEPTH,
+            model: ShadingModel::Phong,
+            roughness: roughness_from_spec(spec),
+            metalness: 0.0,
+            f0: DEFAULT_F0,
+            specular_map: None,
         }
     }
 
-    // Obtener el color de la textura activa
-    pub fn get_diffuse_color(&self, u: f32, v: f32) -> Color {
-        if self.has_texture {
-            let texture = &TEXTURES[self.texture_index]; // Usar la textura por índice
-            let x = (u * (texture.width as f32 - 1.0)) as usize;
-            let y = ((1.0 - v) * (texture.height as f32 - 1.0)) as usize;
-            return texture.get_color(x, y);
+    pub fn with_alpha_cutoff(mut self, cutoff: f32) -> Self {
+        self.alpha_cutoff = Some(cutoff);
+        self
+    }
+
+    pub fn with_sampler(mut self, sampler: Sampler) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    pub fn with_relief(mut self, normal_map: Option<TextureHandle>, height_map: Option<TextureHandle>, depth: f32) -> Self {
+        self.normal_map = normal_map;
+        self.height_map = height_map;
+        self.relief_depth = depth;
+        self
+    }
+
+    pub fn with_shading(mut self, model: ShadingModel) -> Self {
+        self.model = model;
+        self
+    }
+
+    // Parámetros del modelo PBR; solo se usan con `ShadingModel::Pbr`
+    pub fn with_pbr(mut self, roughness: f32, metalness: f32, f0: f32, specular_map: Option<TextureHandle>) -> Self {
+        self.roughness = roughness;
+        self.metalness = metalness;
+        self.f0 = f0;
+        self.specular_map = specular_map;
+        self
+    }
+
+    // Convierte el material en emisivo
+    pub fn with_emission(mut self, emission: Color, strength: f32, mask: Option<TextureHandle>) -> Self {
+        self.emission = emission;
+        self.emission_strength = strength;
+        self.emission_texture = mask;
+        self
+    }
+
+    pub fn with_face_texture(mut self, face: Face, texture: FaceTexture) -> Self {
+        let faces = self.faces.get_or_insert_with(Default::default);
+        Arc::make_mut(faces)[face.index()] = Some(texture);
+        self
+    }
+
+    // Material para una caja que ocupa solo la parte `from`..`to` del bloque
+    // (coordenadas [0, 1]): cada cara muestra el recorte de la textura que le
+    // corresponde, como en Minecraft, en lugar de estirarla entera
+    pub fn cropped(&self, from: &Vec3, to: &Vec3) -> Material {
+        let mut material = self.clone();
+        for face in Face::ALL {
+            let own = self.faces.as_ref().and_then(|faces| faces[face.index()].as_ref());
+            let face_texture = match (own, &self.texture) {
+                (Some(face_texture), _) => face_texture.clone(),
+                (None, Some(texture)) => FaceTexture {
+                    texture: texture.clone(),
+                    rotation: 0,
+                    uv: FaceTexture::FULL_UV,
+                },
+                (None, None) => continue,
+            };
+            let a = face.uv_at(from);
+            let b = face.uv_at(to);
+            let cropped = face_texture.crop((a.0.min(b.0), a.0.max(b.0)), (a.1.min(b.1), a.1.max(b.1)));
+            material = material.with_face_texture(face, cropped);
+        }
+        material
+    }
+
+    // Material tal como se ve desde una cara, con las coordenadas UV de la cara
+    // ya llevadas a su textura
+    pub fn for_face(&self, face: Face, uv: (f32, f32)) -> (Material, (f32, f32)) {
+        let mut material = self.clone();
+        match self.faces.as_ref().and_then(|faces| faces[face.index()].as_ref()) {
+            Some(face_texture) => {
+                material.texture = Some(face_texture.texture.clone());
+                (material, face_texture.map_uv(uv))
+            }
+            None => (material, uv),
+        }
+    }
+
+    // Direcciones en las que crecen u y v de la textura sobre una cara
+    pub fn face_tangents(&self, face: Face) -> (Vec3, Vec3) {
+        match self.faces.as_ref().and_then(|faces| faces[face.index()].as_ref()) {
+            Some(face_texture) => face_texture.tangents(face.uv_axes()),
+            None => face.uv_axes(),
+        }
+    }
+
+    // Normal de sombreado y UV con el relieve aplicado: el parallax desplaza
+    // las UV según la altura vista desde el rayo y el mapa de normales inclina
+    // la normal. `tangents` son las direcciones de u y v sobre la superficie.
+    pub fn relief(
+        &self,
+        normal: &Vec3,
+        (tangent, bitangent): (Vec3, Vec3),
+        uv: (f32, f32),
+        ray_direction: &Vec3,
+        time: f32,
+    ) -> (Vec3, (f32, f32)) {
+        let labpbr = self.texture.as_ref().and_then(|texture| texture.normal_map.as_ref());
+        if self.normal_map.is_none() && self.height_map.is_none() && labpbr.is_none() {
+            return (*normal, uv);
+        }
+
+        // Altura en [0, 1]: del mapa propio, o del alfa del `_n` de LabPBR
+        let height = |(u, v): (f32, f32)| match (&self.height_map, labpbr) {
+            (Some(map), _) => map.sample(u, v, time, &self.sampler, 0.0).r,
+            (None, Some(map)) => map.alpha(u, v, time, self.sampler.wrap),
+            (None, None) => 1.0,
+        };
+
+        // Parallax: se avanza por capas a lo largo del rayo, en espacio
+        // tangente, hasta quedar por debajo de la superficie
+        let mut uv = uv;
+        let direction = Vec3::new(ray_direction.dot(&tangent), ray_direction.dot(&bitangent), ray_direction.dot(normal));
+        if self.relief_depth > 0.0 && direction.z < 0.0 {
+            let layer = 1.0 / PARALLAX_STEPS as f32;
+            // Los rayos rasantes se limitan para no cruzar media textura
+            let scale = self.relief_depth * layer / (-direction.z).max(0.2);
+            let step = (direction.x * scale, direction.y * scale);
+            let mut depth = 0.0;
+            for _ in 0..PARALLAX_STEPS {
+                if depth >= 1.0 - height(uv) {
+                    break;
+                }
+                uv = (uv.0 + step.0, uv.1 + step.1);
+                depth += layer;
+            }
+        }
+
+        let local = if let Some(map) = &self.normal_map {
+            let color = map.sample(uv.0, uv.1, time, &self.sampler, 0.0);
+            Vec3::new(color.r * 2.0 - 1.0, color.g * 2.0 - 1.0, color.b * 2.0 - 1.0)
+        } else if let Some(map) = labpbr {
+            // LabPBR guarda X e Y en rojo y verde, con Y hacia abajo (DirectX);
+            // Z se reconstruye
+            let color = map.sample(uv.0, uv.1, time, &self.sampler, 0.0);
+            let (x, y) = (color.r * 2.0 - 1.0, 1.0 - color.g * 2.0);
+            Vec3::new(x, y, (1.0 - x * x - y * y).max(0.0).sqrt())
+        } else {
+            // Pendientes del mapa de alturas, con diferencias de un texel
+            let (du, dv) = self.height_map.as_ref().map_or((0.01, 0.01), |map| map.texel_size());
+            let slope_u = (height((uv.0 + du, uv.1)) - height((uv.0 - du, uv.1))) / (2.0 * du);
+            let slope_v = (height((uv.0, uv.1 + dv)) - height((uv.0, uv.1 - dv))) / (2.0 * dv);
+            Vec3::new(-slope_u * self.relief_depth, -slope_v * self.relief_depth, 1.0)
+        };
+        let shading = (tangent * local.x + bitangent * local.y + normal * local.z).try_normalize(1e-6);
+        (shading.unwrap_or(*normal), uv)
+    }
+
+    // Fracción de la textura que muestra una cara (1 sin recorte)
+    pub fn face_uv_area(&self, face: Face) -> f32 {
+        match self.faces.as_ref().and_then(|faces| faces[face.index()].as_ref()) {
+            Some(face_texture) => {
+                let [u1, v1, u2, v2] = face_texture.uv;
+                ((u2 - u1) * (v2 - v1)).abs()
+            }
+            None => 1.0,
+        }
+    }
+
+    // Si en (u, v) la textura está recortada y el rayo debe seguir de largo
+    pub fn is_cut_out(&self, u: f32, v: f32, time: f32) -> bool {
+        match (self.alpha_cutoff, &self.texture) {
+            (Some(cutoff), Some(texture)) => texture.alpha(u, v, time, self.sampler.wrap) < cutoff,
+            _ => false,
+        }
+    }
+
+    pub fn is_emissive(&self) -> bool {
+        self.emission_strength > 0.0 && self.emission != Color::black()
+    }
+
+    // Obtener el color de la textura activa, en lineal. `time` (segundos)
+    // elige el cuadro de las texturas animadas y `footprint` (ancho del pixel
+    // en unidades UV) el mipmap.
+    pub fn get_diffuse_color(&self, u: f32, v: f32, time: f32, footprint: f32) -> Radiance {
+        if let Some(texture) = &self.texture {
+            return texture.sample(u, v, time, &self.sampler, footprint);
+        }
+        Radiance::from_srgb(self.diffuse) // Fallback a color difuso si no hay textura
+    }
+
+    // Radiancia emitida en (u, v)
+    pub fn get_emission(&self, u: f32, v: f32, time: f32, footprint: f32) -> Radiance {
+        if !self.is_emissive() {
+            return Radiance::black();
+        }
+        let emission = Radiance::from_srgb(self.emission) * self.emission_strength;
+        match &self.emission_texture {
+            Some(texture) => emission * texture.sample(u, v, time, &self.sampler, footprint),
+            None => emission,
+        }
+    }
+
+    // Parámetros PBR en (u, v). Un mapa `_s` de LabPBR los reemplaza: rojo es
+    // la suavidad, verde el F0 (o, desde 230, un metal), y un alfa menor a
+    // 255 hace que la superficie emita
+    pub fn pbr_params(&self, u: f32, v: f32, time: f32, footprint: f32) -> PbrParams {
+        let params = PbrParams {
+            roughness: self.roughness,
+            metalness: self.metalness,
+            f0: self.f0,
+            emission: 0.0,
+        };
+        let labpbr = self.texture.as_ref().and_then(|texture| texture.specular_map.as_ref());
+        let Some(map) = self.specular_map.as_ref().or(labpbr) else {
+            return params;
+        };
+
+        let specular = map.sample(u, v, time, &self.sampler, footprint);
+        let alpha = map.alpha(u, v, time, self.sampler.wrap);
+        let (metalness, f0) = if specular.g >= LABPBR_METAL_START {
+            (1.0, params.f0)
+        } else {
+            (0.0, specular.g)
+        };
+        PbrParams {
+            roughness: 1.0 - specular.r,
+            metalness,
+            f0,
+            emission: if alpha < 1.0 { (alpha * 255.0 / 254.0).min(1.0) } else { 0.0 },
+        }
+    }
+
+    // Radiancia emitida promedio sobre toda la cara
+    pub fn average_emission(&self) -> Radiance {
+        if !self.is_emissive() {
+            return Radiance::black();
+        }
+        let emission = Radiance::from_srgb(self.emission) * self.emission_strength;
+        match &self.emission_texture {
+            Some(texture) => emission * texture.average(),
+            None => emission,
         }
-        self.diffuse // Fallback a color difuso si no hay textura
     }
 
     pub fn black() -> Self {
@@ -109,8 +471,21 @@ impl Material {
             reflectivity: 0.0,
             transparency: 0.0,
             refraction_index: 0.0,
-            has_texture: false,
-            texture_index: 0, // Default a 0, sin textura
+            texture: None,
+            faces: None,
+            emission: Color::black(),
+            emission_strength: 0.0,
+            emission_texture: None,
+            alpha_cutoff: None,
+            sampler: Sampler::default(),
+            normal_map: None,
+            height_map: None,
+            relief_depth: DEFAULT_RELIEF_DEPTH,
+            model: ShadingModel::Phong,
+            roughness: 1.0,
+            metalness: 0.0,
+            f0: DEFAULT_F0,
+            specular_map: None,
         }
     }
 }
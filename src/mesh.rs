@@ -0,0 +1,191 @@
+use std::sync::Arc;
+
+use nalgebra_glm::Vec3;
+
+use crate::aabb::Aabb;
+use crate::bvh::Bvh;
+use crate::material::Material;
+use crate::ray_intersect::{Intersect, RayIntersect};
+
+// Por debajo de este determinante el rayo se considera paralelo al triángulo
+const EPSILON: f32 = 1e-8;
+
+// Triángulo con normales y UV por vértice. El material se comparte entre
+// todos los triángulos que lo usan.
+pub struct Triangle {
+    pub vertices: [Vec3; 3],
+    // Sin normales por vértice se usa la normal geométrica (orden antihorario)
+    pub normals: Option<[Vec3; 3]>,
+    pub uvs: [(f32, f32); 3],
+    pub material: Arc<Material>,
+}
+
+impl Triangle {
+    // Möller–Trumbore: distancia y coordenadas baricéntricas (u, v) del impacto
+    fn hit(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Option<(f32, f32, f32)> {
+        let [a, b, c] = &self.vertices;
+        let edge1 = b - a;
+        let edge2 = c - a;
+
+        let p = ray_direction.cross(&edge2);
+        let det = edge1.dot(&p);
+        if det.abs() < EPSILON {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        let s = ray_origin - a;
+        let u = s.dot(&p) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(&edge1);
+        let v = ray_direction.dot(&q) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = edge2.dot(&q) * inv_det;
+        if t > 0.0 {
+            Some((t, u, v))
+        } else {
+            None
+        }
+    }
+
+    // Área en UV por área en el espacio, como raíz: unidades UV por unidad de largo
+    fn uv_density(&self) -> f32 {
+        let [a, b, c] = &self.vertices;
+        let [ta, tb, tc] = self.uvs;
+        let uv_area = ((tb.0 - ta.0) * (tc.1 - ta.1) - (tc.0 - ta.0) * (tb.1 - ta.1)).abs();
+        let area = (b - a).cross(&(c - a)).magnitude();
+        (uv_area / area).sqrt()
+    }
+}
+
+impl RayIntersect for Triangle {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3, time: f32) -> Intersect {
+        let Some((t, u, v)) = self.hit(ray_origin, ray_direction) else {
+            return Intersect::empty();
+        };
+        let w = 1.0 - u - v;
+
+        // Las UV fuera de [0, 1] las resuelve el modo de repetición del material
+        let [ta, tb, tc] = self.uvs;
+        let uv = (ta.0 * w + tb.0 * u + tc.0 * v, ta.1 * w + tb.1 * u + tc.1 * v);
+        if self.material.is_cut_out(uv.0, uv.1, time) {
+            return Intersect::empty();
+        }
+
+        let mut normal = match &self.normals {
+            Some([na, nb, nc]) => (na * w + nb * u + nc * v).normalize(),
+            None => {
+                let [a, b, c] = &self.vertices;
+                (b - a).cross(&(c - a)).normalize()
+            }
+        };
+        // Con recorte por alfa el triángulo es una lámina: se ve igual por detrás
+        if self.material.alpha_cutoff.is_some() && normal.dot(ray_direction) > 0.0 {
+            normal = -normal;
+        }
+
+        let point = ray_origin + ray_direction * t;
+        Intersect::new(point, normal, t, (*self.material).clone(), uv).with_uv_density(self.uv_density())
+    }
+
+    fn bounds(&self) -> Aabb {
+        let mut bounds = Aabb::empty();
+        for vertex in &self.vertices {
+            bounds.grow(vertex);
+        }
+        bounds
+    }
+
+    fn any_hit(&self, ray_origin: &Vec3, ray_direction: &Vec3, max_distance: f32, time: f32) -> bool {
+        if self.material.alpha_cutoff.is_some() {
+            // Hace falta la UV para saber si el texel deja pasar la sombra
+            let i = self.ray_intersect(ray_origin, ray_direction, time);
+            return i.is_intersecting && i.distance < max_distance;
+        }
+        self.hit(ray_origin, ray_direction).is_some_and(|(t, _, _)| t < max_distance)
+    }
+}
+
+// Malla de triángulos con su propia BVH, para modelos importados (OBJ)
+pub struct Mesh {
+    triangles: Bvh<Triangle>,
+}
+
+impl Mesh {
+    pub fn new(triangles: Vec<Triangle>) -> Mesh {
+        Mesh {
+            triangles: Bvh::new(triangles),
+        }
+    }
+}
+
+impl RayIntersect for Mesh {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3, time: f32) -> Intersect {
+        self.triangles.ray_intersect(ray_origin, ray_direction, time)
+    }
+
+    fn bounds(&self) -> Aabb {
+        self.triangles.bounds()
+    }
+
+    fn any_hit(&self, ray_origin: &Vec3, ray_direction: &Vec3, max_distance: f32, time: f32) -> bool {
+        self.triangles.any_hit(ray_origin, ray_direction, max_distance, time)
+    }
+}
+
+// Dos planos verticales cruzados sobre las diagonales de una caja, como las
+// plantas, el fuego o las telarañas de Minecraft. Cada plano muestra la
+// textura completa; el material suele usar recorte por alfa.
+pub struct CrossedQuads {
+    triangles: Bvh<Triangle>,
+}
+
+impl CrossedQuads {
+    pub fn new(min: Vec3, max: Vec3, material: Material) -> CrossedQuads {
+        let material = Arc::new(material);
+        let diagonals = [((min.x, min.z), (max.x, max.z)), ((min.x, max.z), (max.x, min.z))];
+
+        let mut triangles = Vec::new();
+        for ((x0, z0), (x1, z1)) in diagonals {
+            let a = Vec3::new(x0, min.y, z0);
+            let b = Vec3::new(x1, min.y, z1);
+            let c = Vec3::new(x1, max.y, z1);
+            let d = Vec3::new(x0, max.y, z0);
+            let quad = [
+                ([a, b, c], [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)]),
+                ([a, c, d], [(0.0, 0.0), (1.0, 1.0), (0.0, 1.0)]),
+            ];
+            for (vertices, uvs) in quad {
+                triangles.push(Triangle {
+                    vertices,
+                    normals: None,
+                    uvs,
+                    material: material.clone(),
+                });
+            }
+        }
+
+        CrossedQuads {
+            triangles: Bvh::new(triangles),
+        }
+    }
+}
+
+impl RayIntersect for CrossedQuads {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3, time: f32) -> Intersect {
+        self.triangles.ray_intersect(ray_origin, ray_direction, time)
+    }
+
+    fn bounds(&self) -> Aabb {
+        self.triangles.bounds()
+    }
+
+    fn any_hit(&self, ray_origin: &Vec3, ray_direction: &Vec3, max_distance: f32, time: f32) -> bool {
+        self.triangles.any_hit(ray_origin, ray_direction, max_distance, time)
+    }
+}
@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+
+use flate2::read::{GzDecoder, ZlibDecoder};
+
+// Lector mínimo del formato NBT (Named Binary Tag) de Minecraft, big-endian
+
+// Se guardan todos los tipos aunque los importadores no lean todos
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(HashMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+#[derive(Debug)]
+pub struct NbtError(pub String);
+
+impl fmt::Display for NbtError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid NBT: {}", self.0)
+    }
+}
+
+impl std::error::Error for NbtError {}
+
+impl Tag {
+    pub fn get(&self, name: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(map) => map.get(name),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&HashMap<String, Tag>> {
+        match self {
+            Tag::Compound(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Tag]> {
+        match self {
+            Tag::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    // Cualquier tag entero, ya que los formatos no siempre usan el mismo ancho
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Tag::Byte(v) => Some(v as i64),
+            Tag::Short(v) => Some(v as i64),
+            Tag::Int(v) => Some(v as i64),
+            Tag::Long(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_byte_array(&self) -> Option<&[i8]> {
+        match self {
+            Tag::ByteArray(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_long_array(&self) -> Option<&[i64]> {
+        match self {
+            Tag::LongArray(longs) => Some(longs),
+            _ => None,
+        }
+    }
+
+    // Lista de enteros, venga como IntArray o como List de Int
+    pub fn as_int_vec(&self) -> Option<Vec<i64>> {
+        match self {
+            Tag::IntArray(ints) => Some(ints.iter().map(|&v| v as i64).collect()),
+            Tag::List(list) => list.iter().map(Tag::as_i64).collect(),
+            _ => None,
+        }
+    }
+}
+
+// Descomprime si hace falta (gzip o zlib) y lee el compound raíz
+pub fn read(bytes: &[u8]) -> Result<Tag, NbtError> {
+    let data = decompress(bytes)?;
+    let mut reader = Reader { data: &data, pos: 0 };
+
+    let tag_type = reader.u8()?;
+    if tag_type != 10 {
+        return Err(NbtError(format!("root tag must be a compound, found type {}", tag_type)));
+    }
+    reader.string()?; // Nombre de la raíz, normalmente vacío
+    reader.payload(tag_type, 0)
+}
+
+fn decompress(bytes: &[u8]) -> Result<Vec<u8>, NbtError> {
+    let mut data = Vec::new();
+    let result = match bytes {
+        [0x1f, 0x8b, ..] => GzDecoder::new(bytes).read_to_end(&mut data),
+        [0x78, ..] => ZlibDecoder::new(bytes).read_to_end(&mut data),
+        _ => return Ok(bytes.to_vec()),
+    };
+    result.map_err(|e| NbtError(format!("decompression failed: {}", e)))?;
+    Ok(data)
+}
+
+// Límite de anidación para no desbordar la pila con archivos corruptos
+const MAX_DEPTH: usize = 512;
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, count: usize) -> Result<&[u8], NbtError> {
+        if self.pos + count > self.data.len() {
+            return Err(NbtError(format!("unexpected end of data at byte {}", self.pos)));
+        }
+        let slice = &self.data[self.pos..self.pos + count];
+        self.pos += count;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, NbtError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn i16(&mut self) -> Result<i16, NbtError> {
+        Ok(i16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, NbtError> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn i64(&mut self) -> Result<i64, NbtError> {
+        Ok(i64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn length(&mut self) -> Result<usize, NbtError> {
+        let length = self.i32()?;
+        if length < 0 {
+            return Err(NbtError(format!("negative length {} at byte {}", length, self.pos)));
+        }
+        Ok(length as usize)
+    }
+
+    fn string(&mut self) -> Result<String, NbtError> {
+        let length = self.i16()? as u16 as usize;
+        // Java usa "modified UTF-8"; para IDs de bloque basta con UTF-8
+        Ok(String::from_utf8_lossy(self.take(length)?).into_owned())
+    }
+
+    fn payload(&mut self, tag_type: u8, depth: usize) -> Result<Tag, NbtError> {
+        if depth > MAX_DEPTH {
+            return Err(NbtError("nesting too deep".to_string()));
+        }
+
+        Ok(match tag_type {
+            1 => Tag::Byte(self.u8()? as i8),
+            2 => Tag::Short(self.i16()?),
+            3 => Tag::Int(self.i32()?),
+            4 => Tag::Long(self.i64()?),
+            5 => Tag::Float(f32::from_bits(self.i32()? as u32)),
+            6 => Tag::Double(f64::from_bits(self.i64()? as u64)),
+            7 => {
+                let length = self.length()?;
+                Tag::ByteArray(self.take(length)?.iter().map(|&b| b as i8).collect())
+            }
+            8 => Tag::String(self.string()?),
+            9 => {
+                let element_type = self.u8()?;
+                let length = self.length()?;
+                let mut list = Vec::with_capacity(length.min(4096));
+                for _ in 0..length {
+                    list.push(self.payload(element_type, depth + 1)?);
+                }
+                Tag::List(list)
+            }
+            10 => {
+                let mut map = HashMap::new();
+                loop {
+                    let child_type = self.u8()?;
+                    if child_type == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    map.insert(name, self.payload(child_type, depth + 1)?);
+                }
+                Tag::Compound(map)
+            }
+            11 => {
+                let length = self.length()?;
+                let mut ints = Vec::with_capacity(length.min(4096));
+                for _ in 0..length {
+                    ints.push(self.i32()?);
+                }
+                Tag::IntArray(ints)
+            }
+            12 => {
+                let length = self.length()?;
+                let mut longs = Vec::with_capacity(length.min(4096));
+                for _ in 0..length {
+                    longs.push(self.i64()?);
+                }
+                Tag::LongArray(longs)
+            }
+            other => return Err(NbtError(format!("unknown tag type {} at byte {}", other, self.pos - 1))),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+
+    // Escritor mínimo para armar archivos de prueba a mano
+    fn name(out: &mut Vec<u8>, text: &str) {
+        out.extend_from_slice(&(text.len() as u16).to_be_bytes());
+        out.extend_from_slice(text.as_bytes());
+    }
+
+    fn sample() -> Vec<u8> {
+        let mut out = vec![10];
+        name(&mut out, "");
+
+        out.push(1);
+        name(&mut out, "byte");
+        out.push(0xfe);
+
+        out.push(3);
+        name(&mut out, "int");
+        out.extend_from_slice(&(-70000_i32).to_be_bytes());
+
+        out.push(8);
+        name(&mut out, "name");
+        name(&mut out, "minecraft:lava");
+
+        // Lista de dos compounds con un short cada uno
+        out.push(9);
+        name(&mut out, "list");
+        out.push(10);
+        out.extend_from_slice(&2_i32.to_be_bytes());
+        for value in [7_i16, -8] {
+            out.push(2);
+            name(&mut out, "v");
+            out.extend_from_slice(&value.to_be_bytes());
+            out.push(0);
+        }
+
+        out.push(12);
+        name(&mut out, "longs");
+        out.extend_from_slice(&2_i32.to_be_bytes());
+        out.extend_from_slice(&i64::MIN.to_be_bytes());
+        out.extend_from_slice(&42_i64.to_be_bytes());
+
+        out.push(0);
+        out
+    }
+
+    fn check_sample(root: &Tag) {
+        assert_eq!(root.get("byte").and_then(Tag::as_i64), Some(-2));
+        assert_eq!(root.get("int").and_then(Tag::as_i64), Some(-70000));
+        assert_eq!(root.get("name").and_then(Tag::as_str), Some("minecraft:lava"));
+        let list = root.get("list").and_then(Tag::as_list).unwrap();
+        let values: Vec<_> = list.iter().map(|entry| entry.get("v").and_then(Tag::as_i64)).collect();
+        assert_eq!(values, [Some(7), Some(-8)]);
+        assert!(matches!(root.get("longs"), Some(Tag::LongArray(longs)) if longs[..] == [i64::MIN, 42]));
+    }
+
+    #[test]
+    fn reads_uncompressed_and_gzip() {
+        let bytes = sample();
+        check_sample(&read(&bytes).unwrap());
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&bytes).unwrap();
+        check_sample(&read(&encoder.finish().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn truncated_input_is_an_error() {
+        let bytes = sample();
+        for end in 0..bytes.len() {
+            assert!(read(&bytes[..end]).is_err(), "prefix of {} bytes", end);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_roots_types_and_lengths() {
+        // Raíz que no es compound
+        assert!(read(&[8, 0, 0, 0, 0]).is_err());
+        // Tipo desconocido
+        assert!(read(&[10, 0, 0, 13, 0, 1, b'x', 0]).is_err());
+        // Largo negativo
+        let mut bytes = vec![10, 0, 0, 11, 0, 1, b'x'];
+        bytes.extend_from_slice(&(-1_i32).to_be_bytes());
+        assert!(read(&bytes).is_err());
+        // Largo enorme sin datos: error, no reserva de memoria gigante
+        let mut bytes = vec![10, 0, 0, 12, 0, 1, b'x'];
+        bytes.extend_from_slice(&i32::MAX.to_be_bytes());
+        assert!(read(&bytes).is_err());
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        // Listas de listas más allá de MAX_DEPTH
+        let mut bytes = vec![10, 0, 0, 9, 0, 1, b'x'];
+        for _ in 0..MAX_DEPTH + 1 {
+            bytes.push(9);
+            bytes.extend_from_slice(&1_i32.to_be_bytes());
+        }
+        let error = read(&bytes).unwrap_err();
+        assert!(error.0.contains("too deep"), "{}", error);
+    }
+}
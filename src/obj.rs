@@ -0,0 +1,419 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use nalgebra_glm::Vec3;
+
+use crate::color::Color;
+use crate::material::{roughness_from_spec, Material, ShadingModel};
+use crate::mesh::{Mesh, Triangle};
+use crate::texture_registry::{TextureError, TextureRegistry};
+
+// Lector de Wavefront OBJ con sus materiales MTL (lo que exporta Blockbench).
+// Se usan posiciones, UV, normales, caras (los polígonos se dividen en
+// triángulos en abanico) y `usemtl`; los grupos y el suavizado se ignoran.
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io { path: PathBuf, source: std::io::Error },
+    Parse { path: PathBuf, line: usize, message: String },
+    Texture(TextureError),
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io { path, source } => write!(f, "cannot read {}: {}", path.display(), source),
+            ObjError::Parse { path, line, message } => write!(f, "{}:{}: {}", path.display(), line, message),
+            ObjError::Texture(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+// Carga el modelo como una malla. Con `material` todas las caras usan ese
+// material en lugar de los del archivo MTL.
+pub fn load(path: &Path, textures: &mut TextureRegistry, material: Option<&Material>) -> Result<Mesh, ObjError> {
+    let source = read(path)?;
+    parse(path, &source, textures, material).map(Mesh::new)
+}
+
+// Triángulos del texto de un OBJ; `path` ubica los archivos MTL y los errores
+fn parse(
+    path: &Path,
+    source: &str,
+    textures: &mut TextureRegistry,
+    material: Option<&Material>,
+) -> Result<Vec<Triangle>, ObjError> {
+    let parse_error = |line: usize, message: String| ObjError::Parse {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+
+    let mut positions: Vec<Vec3> = Vec::new();
+    let mut uvs: Vec<(f32, f32)> = Vec::new();
+    let mut normals: Vec<Vec3> = Vec::new();
+    let mut materials: HashMap<String, Arc<Material>> = HashMap::new();
+    let default = Arc::new(material.cloned().unwrap_or_else(default_material));
+    let mut current = default.clone();
+    let mut triangles = Vec::new();
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let mut parts = line.split_whitespace();
+        let Some(keyword) = parts.next() else {
+            continue;
+        };
+        let args: Vec<&str> = parts.collect();
+
+        match keyword {
+            "v" => positions.push(vec3(&floats(&args, 3).map_err(|e| parse_error(line_number, e))?)),
+            "vn" => normals.push(vec3(&floats(&args, 3).map_err(|e| parse_error(line_number, e))?)),
+            "vt" => {
+                let uv = floats(&args, 2).map_err(|e| parse_error(line_number, e))?;
+                uvs.push((uv[0], uv[1]));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(parse_error(line_number, "a face needs at least 3 vertices".to_string()));
+                }
+                let corners = args
+                    .iter()
+                    .map(|corner| parse_corner(corner, positions.len(), uvs.len(), normals.len()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| parse_error(line_number, e))?;
+
+                for i in 1..corners.len() - 1 {
+                    let corners = [corners[0], corners[i], corners[i + 1]];
+                    let vertex_normals = match corners.map(|c| c.normal) {
+                        [Some(a), Some(b), Some(c)] => Some([normals[a], normals[b], normals[c]]),
+                        _ => None,
+                    };
+                    triangles.push(Triangle {
+                        vertices: corners.map(|c| positions[c.position]),
+                        normals: vertex_normals,
+                        uvs: corners.map(|c| c.uv.map_or((0.0, 0.0), |uv| uvs[uv])),
+                        material: current.clone(),
+                    });
+                }
+            }
+            "mtllib" if material.is_none() => {
+                // El nombre puede tener espacios
+                let name = args.join(" ");
+                let mtl_path = path.parent().unwrap_or(Path::new(".")).join(&name);
+                materials.extend(load_mtl(&mtl_path, textures)?);
+            }
+            "usemtl" if material.is_none() => {
+                let name = args.join(" ");
+                current = materials.get(&name).cloned().ok_or_else(|| {
+                    parse_error(line_number, format!("material `{}` is not defined in any mtllib", name))
+                })?;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(triangles)
+}
+
+// Índices (desde 0) de un vértice de cara: "v", "v/vt", "v//vn" o "v/vt/vn"
+#[derive(Clone, Copy)]
+struct Corner {
+    position: usize,
+    uv: Option<usize>,
+    normal: Option<usize>,
+}
+
+fn parse_corner(text: &str, positions: usize, uvs: usize, normals: usize) -> Result<Corner, String> {
+    let mut fields = text.split('/');
+    let mut index = |count: usize, kind: &str| -> Result<Option<usize>, String> {
+        match fields.next() {
+            None | Some("") => Ok(None),
+            Some(field) => resolve_index(field, count)
+                .map(Some)
+                .ok_or_else(|| format!("invalid {} index `{}` in `{}`", kind, field, text)),
+        }
+    };
+    let position = index(positions, "vertex")?.ok_or_else(|| format!("face vertex `{}` has no position", text))?;
+    let uv = index(uvs, "texture coordinate")?;
+    let normal = index(normals, "normal")?;
+    Ok(Corner { position, uv, normal })
+}
+
+// Los índices OBJ empiezan en 1; los negativos cuentan desde el último leído
+fn resolve_index(field: &str, count: usize) -> Option<usize> {
+    let index: i64 = field.parse().ok()?;
+    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+    (0..count as i64).contains(&resolved).then_some(resolved as usize)
+}
+
+// Materiales de un archivo MTL. Kd es el color difuso, Ns el exponente
+// especular, Ks su peso, d (o Tr) la opacidad, Ni el índice de refracción,
+// Ke la emisión y map_Kd la textura difusa. Las extensiones PBR Pr
+// (rugosidad) y Pm (metalicidad) pasan el material al modelo PBR.
+fn load_mtl(path: &Path, textures: &mut TextureRegistry) -> Result<HashMap<String, Arc<Material>>, ObjError> {
+    let source = read(path)?;
+    parse_mtl(path, &source, textures)
+}
+
+fn parse_mtl(
+    path: &Path,
+    source: &str,
+    textures: &mut TextureRegistry,
+) -> Result<HashMap<String, Arc<Material>>, ObjError> {
+    let parse_error = |line: usize, message: String| ObjError::Parse {
+        path: path.to_path_buf(),
+        line,
+        message,
+    };
+
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, Material)> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let mut parts = line.split_whitespace();
+        let Some(keyword) = parts.next() else {
+            continue;
+        };
+        let args: Vec<&str> = parts.collect();
+
+        if keyword == "newmtl" {
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, Arc::new(material));
+            }
+            current = Some((args.join(" "), default_material()));
+            continue;
+        }
+        let Some((_, material)) = current.as_mut() else {
+            continue;
+        };
+        let values = |count: usize| floats(&args, count).map_err(|e| parse_error(line_number, e));
+
+        match keyword {
+            "Kd" => material.diffuse = color(&values(3)?),
+            "Ks" => {
+                let ks = values(3)?;
+                material.albedo[1] = (ks[0] + ks[1] + ks[2]) / 3.0;
+            }
+            "Ns" => {
+                material.spec = values(1)?[0];
+                // Sin Pr, la rugosidad sigue al exponente de Phong
+                if material.model == ShadingModel::Phong {
+                    material.roughness = roughness_from_spec(material.spec);
+                }
+            }
+            "Pr" => {
+                material.roughness = values(1)?[0].clamp(0.0, 1.0);
+                material.model = ShadingModel::Pbr;
+            }
+            "Pm" => {
+                material.metalness = values(1)?[0].clamp(0.0, 1.0);
+                material.model = ShadingModel::Pbr;
+            }
+            "d" => material.transparency = 1.0 - values(1)?[0],
+            "Tr" => material.transparency = values(1)?[0],
+            "Ni" => material.refraction_index = values(1)?[0],
+            "Ke" => {
+                let emission = color(&values(3)?);
+                if emission != Color::black() {
+                    *material = material.clone().with_emission(emission, 1.0, None);
+                }
+            }
+            "map_Kd" => {
+                // Las opciones (-s, -o...) no se soportan: se toma el último argumento
+                let name = args.last().ok_or_else(|| parse_error(line_number, "missing texture name".to_string()))?;
+                let local = path.parent().unwrap_or(Path::new(".")).join(name);
+                let name = if local.is_file() { local.to_string_lossy().into_owned() } else { name.to_string() };
+                material.texture = Some(textures.load(&name).map_err(ObjError::Texture)?);
+            }
+            _ => {}
+        }
+    }
+    if let Some((name, material)) = current {
+        materials.insert(name, Arc::new(material));
+    }
+    Ok(materials)
+}
+
+fn read(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|source| ObjError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+// Los primeros `count` argumentos como números (los extra, como `w`, se ignoran)
+fn floats(args: &[&str], count: usize) -> Result<Vec<f32>, String> {
+    if args.len() < count {
+        return Err(format!("expected {} numbers, found {}", count, args.len()));
+    }
+    args[..count]
+        .iter()
+        .map(|arg| arg.parse::<f32>().map_err(|_| format!("invalid number `{}`", arg)))
+        .collect()
+}
+
+fn vec3(values: &[f32]) -> Vec3 {
+    Vec3::new(values[0], values[1], values[2])
+}
+
+// Color MTL en [0, 1] a 8 bits
+fn color(values: &[f32]) -> Color {
+    let channel = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as i32;
+    Color::new(channel(values[0]), channel(values[1]), channel(values[2]))
+}
+
+// Material de las caras sin `usemtl`, y base de los materiales MTL
+fn default_material() -> Material {
+    Material::new(Color::new(200, 200, 200), 10.0, [0.9, 0.1], 0.0, 0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_text(source: &str) -> Result<Vec<Triangle>, ObjError> {
+        let mut textures = TextureRegistry::new(Vec::new());
+        parse(Path::new("inline/model.obj"), source, &mut textures, None)
+    }
+
+    fn error_line(result: Result<Vec<Triangle>, ObjError>) -> usize {
+        match result {
+            Err(ObjError::Parse { line, .. }) => line,
+            Err(e) => panic!("expected a parse error, got {}", e),
+            Ok(_) => panic!("expected a parse error"),
+        }
+    }
+
+    const SQUARE: &str = "\
+# cuadrado en el plano XY
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+";
+
+    #[test]
+    fn splits_polygons_into_a_fan() {
+        let triangles = parse_text(&format!("{}f 1/1/1 2/2/1 3/3/1 4/4/1\n", SQUARE)).unwrap();
+        assert_eq!(triangles.len(), 2);
+        let [a, b, c, d] = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]].map(|[x, y]| Vec3::new(x, y, 0.0));
+        assert_eq!(triangles[0].vertices, [a, b, c]);
+        assert_eq!(triangles[1].vertices, [a, c, d]);
+        assert_eq!(triangles[1].uvs, [(0.0, 0.0), (1.0, 1.0), (0.0, 1.0)]);
+        assert_eq!(triangles[0].normals, Some([Vec3::z(); 3]));
+    }
+
+    #[test]
+    fn reads_every_corner_format() {
+        let faces = "f 1 2 3\nf 1/1 2/2 3/3\nf 1//1 2//1 3//1\nf 1/1/1 2/2/1 3/3/1\n";
+        let triangles = parse_text(&format!("{}{}", SQUARE, faces)).unwrap();
+        assert_eq!(triangles.len(), 4);
+        // Sin UV se usa (0, 0); sin normales, la normal geométrica
+        assert_eq!(triangles[0].uvs, [(0.0, 0.0); 3]);
+        assert!(triangles[0].normals.is_none());
+        assert_eq!(triangles[1].uvs, [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)]);
+        assert!(triangles[1].normals.is_none());
+        assert_eq!(triangles[2].uvs, [(0.0, 0.0); 3]);
+        assert_eq!(triangles[2].normals, Some([Vec3::z(); 3]));
+        assert_eq!(triangles[3].uvs, triangles[1].uvs);
+        assert_eq!(triangles[3].normals, triangles[2].normals);
+    }
+
+    #[test]
+    fn negative_indices_count_from_the_last_vertex() {
+        let relative = parse_text(&format!("{}f -4/-4/-1 -3/-3/-1 -2/-2/-1\n", SQUARE)).unwrap();
+        let absolute = parse_text(&format!("{}f 1/1/1 2/2/1 3/3/1\n", SQUARE)).unwrap();
+        assert_eq!(relative[0].vertices, absolute[0].vertices);
+        assert_eq!(relative[0].uvs, absolute[0].uvs);
+        assert_eq!(relative[0].normals, absolute[0].normals);
+
+        // Cuentan desde lo leído hasta esa línea, no hasta el final del archivo
+        let triangles = parse_text("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -3 -2 -1\nv 5 5 5\n").unwrap();
+        assert_eq!(triangles[0].vertices[0], Vec3::zeros());
+    }
+
+    #[test]
+    fn out_of_range_indices_are_errors() {
+        for face in ["f 0 1 2", "f 1 2 5", "f -5 1 2", "f 1/9 2 3", "f 1//2 2 3", "f a 2 3", "f /1 2 3"] {
+            assert_eq!(error_line(parse_text(&format!("{}{}\n", SQUARE, face))), 11, "{}", face);
+        }
+    }
+
+    #[test]
+    fn malformed_lines_report_their_line() {
+        assert_eq!(error_line(parse_text("v 0 0\n")), 1);
+        assert_eq!(error_line(parse_text("v 0 0 0\nvt x 0\n")), 2);
+        assert_eq!(error_line(parse_text("v 0 0 0\nv 1 0 0\n\nf 1 2\n")), 4);
+        // Las palabras desconocidas, los grupos y los valores extra se ignoran
+        let source = "o cubo\ng lado\ns 1\nv 0 0 0 1\nv 1 0 0\nv 0 1 0\nvt 0 0 0\nf 1/1 2/1 3/1\n";
+        let triangles = parse_text(source).unwrap();
+        assert_eq!(triangles.len(), 1);
+    }
+
+    #[test]
+    fn missing_mtllib_and_materials_are_errors() {
+        match parse_text("mtllib missing.mtl\n") {
+            Err(ObjError::Io { path, .. }) => assert_eq!(path, Path::new("inline/missing.mtl")),
+            Err(e) => panic!("expected an io error, got {}", e),
+            Ok(_) => panic!("expected an io error"),
+        }
+        assert_eq!(error_line(parse_text(&format!("{}usemtl piedra\nf 1 2 3\n", SQUARE))), 11);
+
+        // Con un material de la escena, los del archivo no se leen
+        let mut textures = TextureRegistry::new(Vec::new());
+        let material = Material::new(Color::new(10, 20, 30), 10.0, [0.9, 0.1], 0.0, 0.0, 0.0);
+        let source = format!("mtllib missing.mtl\n{}usemtl piedra\nf 1 2 3\n", SQUARE);
+        let triangles = parse(Path::new("inline/model.obj"), &source, &mut textures, Some(&material)).unwrap();
+        assert_eq!(triangles[0].material.diffuse, Color::new(10, 20, 30));
+    }
+
+    #[test]
+    fn reads_mtl_materials() {
+        let mut textures = TextureRegistry::new(Vec::new());
+        let source = "\
+Kd 1 1 1
+newmtl piedra
+Kd 0.5 0 1
+Ns 50
+Ks 0.2 0.4 0.6
+d 0.25
+Ni 1.5
+
+newmtl oro
+Pm 1
+Pr 0.3
+Ke 1 0.5 0
+";
+        let materials = parse_mtl(Path::new("inline/model.mtl"), source, &mut textures).unwrap();
+        assert_eq!(materials.len(), 2);
+
+        let stone = &materials["piedra"];
+        assert_eq!(stone.diffuse, Color::new(128, 0, 255));
+        assert_eq!(stone.spec, 50.0);
+        assert!((stone.albedo[1] - 0.4).abs() < 1e-6);
+        assert_eq!(stone.transparency, 0.75);
+        assert_eq!(stone.refraction_index, 1.5);
+        assert_eq!(stone.model, ShadingModel::Phong);
+        assert!(!stone.is_emissive());
+
+        let gold = &materials["oro"];
+        assert_eq!(gold.model, ShadingModel::Pbr);
+        assert_eq!(gold.metalness, 1.0);
+        assert_eq!(gold.roughness, 0.3);
+        assert!(gold.is_emissive());
+
+        let error = parse_mtl(Path::new("inline/model.mtl"), "newmtl roto\nKd 1 1\n", &mut textures);
+        assert!(matches!(error, Err(ObjError::Parse { line: 2, .. })));
+    }
+}
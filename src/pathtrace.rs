@@ -0,0 +1,323 @@
+use std::f32::consts::PI;
+
+use nalgebra_glm::Vec3;
+
+use crate::brdf;
+use crate::castray::{self, RenderContext};
+use crate::cube::Cube;
+use crate::material::{Material, PbrParams, ShadingModel};
+use crate::radiance::Radiance;
+use crate::sampling::Rng;
+
+// Trazado de caminos Monte Carlo: cada pasada lanza un camino por pixel que
+// rebota al azar por la escena, y el promedio de las pasadas converge a la
+// iluminación global (la luz roja que rebota del netherrack y la lava).
+
+const MAX_BOUNCES: u32 = 16;
+// Rebotes antes de que la ruleta rusa pueda cortar el camino
+const ROULETTE_START: u32 = 3;
+// Tolerancia para aceptar que un rayo de sombra llegó al punto muestreado de un emisor
+const EMITTER_EPSILON: f32 = 1e-3;
+
+// Cómo se calcula el color de cada pixel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Integrator {
+    // Luz directa con reflejos y refracciones perfectos (`cast_ray`)
+    Whitted,
+    // Caminos de luz acumulados en varias pasadas
+    Path,
+}
+
+impl Integrator {
+    pub fn parse(name: &str) -> Option<Integrator> {
+        match name {
+            "whitted" => Some(Integrator::Whitted),
+            "path" => Some(Integrator::Path),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Integrator::Whitted => "whitted",
+            Integrator::Path => "path",
+        }
+    }
+
+    // Siguiente integrador, para alternar desde la ventana
+    pub fn next(self) -> Integrator {
+        match self {
+            Integrator::Whitted => Integrator::Path,
+            Integrator::Path => Integrator::Whitted,
+        }
+    }
+}
+
+// Suma de las pasadas de cada pixel; se reinicia cuando cambia la vista
+pub struct Accumulator {
+    pub sum: Vec<Radiance>,
+    pub samples: u32,
+}
+
+impl Accumulator {
+    pub fn new(len: usize) -> Accumulator {
+        Accumulator {
+            sum: vec![Radiance::black(); len],
+            samples: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.sum.fill(Radiance::black());
+        self.samples = 0;
+    }
+}
+
+// Cara de un cubo emisivo: esquina, lados y normal hacia afuera
+struct EmitterFace {
+    corner: Vec3,
+    edge_u: Vec3,
+    edge_v: Vec3,
+    normal: Vec3,
+}
+
+// Caras de los cubos emisivos para muestrear su luz directamente. Cada cara
+// se elige con probabilidad proporcional a su área, así la densidad por
+// unidad de área es la misma en todas y no hace falta saber qué cubo tocó un
+// rayo para pesarlo. Los modelos emisivos que no son cubos no entran y solo
+// iluminan cuando un rebote los encuentra; `Intersect::emitter` dice cuáles sí.
+#[derive(Default)]
+pub struct Emitters {
+    faces: Vec<EmitterFace>,
+    // Área acumulada hasta cada cara, incluida
+    cumulative: Vec<f32>,
+    total_area: f32,
+}
+
+impl Emitters {
+    pub fn from_cubes(cubes: &[Cube]) -> Emitters {
+        let mut emitters = Emitters::default();
+        for cube in cubes.iter().filter(|cube| cube.material.is_emissive()) {
+            let size = cube.max - cube.min;
+            for axis in 0..3 {
+                let (a, b) = ((axis + 1) % 3, (axis + 2) % 3);
+                let area = size[a] * size[b];
+                if area <= 0.0 {
+                    continue;
+                }
+                let mut edge_u = Vec3::zeros();
+                edge_u[a] = size[a];
+                let mut edge_v = Vec3::zeros();
+                edge_v[b] = size[b];
+                for (side, sign) in [(cube.min[axis], -1.0), (cube.max[axis], 1.0)] {
+                    let mut corner = cube.min;
+                    corner[axis] = side;
+                    let mut normal = Vec3::zeros();
+                    normal[axis] = sign;
+                    emitters.total_area += area;
+                    emitters.cumulative.push(emitters.total_area);
+                    emitters.faces.push(EmitterFace { corner, edge_u, edge_v, normal });
+                }
+            }
+        }
+        emitters
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.faces.is_empty()
+    }
+
+    // Punto al azar sobre las caras y la normal en él
+    fn sample(&self, rng: &mut Rng) -> (Vec3, Vec3) {
+        let target = rng.next_f32() * self.total_area;
+        let index = self.cumulative.partition_point(|&area| area <= target).min(self.faces.len() - 1);
+        let face = &self.faces[index];
+        let point = face.corner + face.edge_u * rng.next_f32() + face.edge_v * rng.next_f32();
+        (point, face.normal)
+    }
+
+    // Densidad de `sample` en ángulo sólido, vista a `distance` del punto y
+    // con el coseno `cos_light` sobre la cara
+    fn pdf(&self, distance: f32, cos_light: f32) -> f32 {
+        distance * distance / (cos_light.max(1e-4) * self.total_area)
+    }
+}
+
+// Peso de la heurística de la potencia para dos estrategias de muestreo
+fn power_heuristic(pdf: f32, other: f32) -> f32 {
+    let (a, b) = (pdf * pdf, other * other);
+    if a + b > 0.0 { a / (a + b) } else { 0.0 }
+}
+
+// Dirección en el hemisferio de `normal` con densidad coseno/π
+fn cosine_sample(normal: &Vec3, rng: &mut Rng) -> Vec3 {
+    let helper = if normal.x.abs() < 0.9 { Vec3::x() } else { Vec3::y() };
+    let u = normal.cross(&helper).normalize();
+    let v = normal.cross(&u);
+    let r = rng.next_f32().sqrt();
+    let angle = 2.0 * PI * rng.next_f32();
+    let height = (1.0 - r * r).max(0.0).sqrt();
+    (u * (r * angle.cos()) + v * (r * angle.sin()) + normal * height).normalize()
+}
+
+// Luz reflejada hacia `view` por una luz de irradiancia 1 desde `light`, ya
+// multiplicada por el coseno, como `brdf::ggx`. En Phong solo cuenta la parte
+// difusa: el brillo especular y los reflejos salen de `reflectivity`.
+fn reflected(
+    model: ShadingModel,
+    material: &Material,
+    normal: &Vec3,
+    view: &Vec3,
+    light: &Vec3,
+    albedo: Radiance,
+    pbr: &PbrParams,
+) -> Radiance {
+    match model {
+        ShadingModel::Pbr => brdf::ggx(normal, view, light, albedo, pbr),
+        ShadingModel::Phong => albedo * (material.albedo[0] * normal.dot(light).max(0.0)),
+    }
+}
+
+// Radiancia que llega a `ray_origin` desde `ray_direction` por un camino al
+// azar. En cada superficie difusa suma la luz directa de las luces de la
+// escena y de un punto de los emisores (next-event estimation), y sigue en
+// una dirección con densidad coseno. La luz de los emisores que el rebote
+// encuentra se combina con la muestreada directamente por importancia
+// múltiple (MIS), así ninguna de las dos estrategias la cuenta dos veces.
+pub fn trace_path(ray_origin: &Vec3, ray_direction: &Vec3, context: &RenderContext, rng: &mut Rng) -> Radiance {
+    let RenderContext { objects, daylight, lights, emitters, time, .. } = *context;
+
+    let mut origin = *ray_origin;
+    let mut direction = *ray_direction;
+    let mut radiance = Radiance::black();
+    let mut throughput = Radiance::gray(1.0);
+    // Densidad en ángulo sólido del último rebote difuso; None desde la
+    // cámara o tras un reflejo perfecto, que la luz directa no puede muestrear
+    let mut bounce_pdf: Option<f32> = None;
+
+    for bounce in 0..MAX_BOUNCES {
+        let intersect = objects.ray_intersect(&origin, &direction, time);
+        if !intersect.is_intersecting {
+            let background = Radiance::from_srgb(castray::calculate_background_color(daylight));
+            radiance += throughput * background;
+            break;
+        }
+
+        let material = &intersect.material;
+        let (u, v) = intersect.uv;
+        // El mipmap se elige solo con el cono de la cámara; los rebotes son difusos
+        let footprint = if bounce == 0 {
+            let cosine = intersect.normal.dot(&direction).abs().max(0.05);
+            context.pixel_spread * intersect.distance * intersect.uv_density / cosine
+        } else {
+            0.0
+        };
+        let albedo = material.get_diffuse_color(u, v, time, footprint);
+        let model = context.shading.unwrap_or(material.model);
+        let pbr = material.pbr_params(u, v, time, footprint);
+        let view_dir = -direction;
+
+        // Luz propia de la superficie. Solo la de las caras de `Emitters` se
+        // muestrea también como luz directa y se pesa con MIS; la de modelos,
+        // cubos transformados o mapas LabPBR solo llega por los rebotes
+        let emission = material.get_emission(u, v, time, footprint);
+        if emission.average() > 0.0 {
+            let weight = match bounce_pdf {
+                Some(pdf) if intersect.emitter => {
+                    let cos_light = intersect.normal.dot(&view_dir).abs();
+                    power_heuristic(pdf, emitters.pdf(intersect.distance, cos_light))
+                }
+                _ => 1.0,
+            };
+            radiance += throughput * emission * weight;
+        }
+        if model == ShadingModel::Pbr {
+            radiance += throughput * albedo * pbr.emission;
+        }
+
+        // Reflejo y refracción perfectos, elegidos al azar según su peso: el
+        // peso y la probabilidad se cancelan y el camino conserva su energía
+        let choice = rng.next_f32();
+        if choice < material.reflectivity + material.transparency {
+            direction = if choice < material.reflectivity {
+                castray::reflect(&direction, &intersect.normal).normalize()
+            } else {
+                castray::refract(&direction, &intersect.normal, material.refraction_index).normalize()
+            };
+            origin = castray::offset_origin(&intersect, &direction);
+            bounce_pdf = None;
+            continue;
+        }
+
+        // Superficie difusa vista desde el lado del rayo
+        let normal = if intersect.normal.dot(&view_dir) < 0.0 { -intersect.normal } else { intersect.normal };
+
+        // Luces de la escena. Las generadas desde bloques emisivos se saltan:
+        // esa luz ya llega desde los emisores.
+        for light in std::iter::once(daylight).chain(lights).filter(|light| !light.from_blocks) {
+            let attenuation = light.falloff(&intersect.point);
+            if attenuation <= 0.0 {
+                continue;
+            }
+            let (light_dir, _) = light.incidence(&intersect.point);
+            let response = reflected(model, material, &normal, &view_dir, &light_dir, albedo, &pbr);
+            if response.average() <= 0.0 {
+                continue;
+            }
+            let visibility = castray::cast_shadow(&intersect, light, objects, time);
+            let light_color = Radiance::from_srgb(light.color) * (light.intensity * attenuation);
+            radiance += throughput * response * light_color * visibility;
+        }
+
+        // Un punto de los emisores
+        if !emitters.is_empty() {
+            let (target, light_normal) = emitters.sample(rng);
+            let to_light = target - intersect.point;
+            let distance = to_light.magnitude();
+            let light_dir = to_light / distance;
+            let cos_light = light_normal.dot(&-light_dir);
+            let cos_surface = normal.dot(&light_dir);
+            if distance > 0.0 && cos_light > 0.0 && cos_surface > 0.0 {
+                let shadow_origin = castray::offset_origin(&intersect, &light_dir);
+                let hit = objects.ray_intersect(&shadow_origin, &light_dir, time);
+                if hit.is_intersecting && (hit.point - target).magnitude() < EMITTER_EPSILON {
+                    let (hu, hv) = hit.uv;
+                    let light_emission = hit.material.get_emission(hu, hv, time, 0.0);
+                    let light_pdf = emitters.pdf(distance, cos_light);
+                    let weight = power_heuristic(light_pdf, cos_surface / PI);
+                    // `reflected` incluye un factor π que la radiancia del emisor no lleva
+                    let response = reflected(model, material, &normal, &view_dir, &light_dir, albedo, &pbr);
+                    radiance += throughput * response * light_emission * (weight / (PI * light_pdf));
+                }
+            }
+        }
+
+        // Rebote difuso: con densidad coseno/π el peso es `reflected` / coseno
+        let next = cosine_sample(&normal, rng);
+        let cosine = normal.dot(&next);
+        if cosine <= 0.0 {
+            break;
+        }
+        let response = reflected(model, material, &normal, &view_dir, &next, albedo, &pbr);
+        throughput = throughput * response * (1.0 / cosine);
+        bounce_pdf = Some(cosine / PI);
+        direction = next;
+        origin = castray::offset_origin(&intersect, &direction);
+
+        // Ruleta rusa: los caminos que ya llevan poca energía se cortan al
+        // azar, y los que siguen se compensan
+        let peak = throughput.r.max(throughput.g).max(throughput.b);
+        if peak <= 0.0 {
+            break;
+        }
+        if bounce >= ROULETTE_START {
+            let survival = peak.min(0.95);
+            if rng.next_f32() >= survival {
+                break;
+            }
+            throughput = throughput * (1.0 / survival);
+        }
+    }
+
+    radiance
+}
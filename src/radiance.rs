@@ -0,0 +1,99 @@
+use std::ops::{Add, AddAssign, Mul};
+
+use crate::color::Color;
+use crate::srgb;
+
+// Radiancia lineal en punto flotante, sin límite superior. 1.0 equivale
+// al blanco de pantalla antes del mapeo de tonos.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Radiance {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+}
+
+impl Radiance {
+    pub const fn black() -> Radiance {
+        Radiance { r: 0.0, g: 0.0, b: 0.0 }
+    }
+
+    pub const fn gray(value: f32) -> Radiance {
+        Radiance { r: value, g: value, b: value }
+    }
+
+    // Promedio de los tres canales
+    pub fn average(self) -> f32 {
+        (self.r + self.g + self.b) / 3.0
+    }
+
+    pub fn map(self, f: impl Fn(f32) -> f32) -> Radiance {
+        Radiance {
+            r: f(self.r),
+            g: f(self.g),
+            b: f(self.b),
+        }
+    }
+}
+
+impl Radiance {
+    // Color de 8 bits en sRGB (texturas, escenas) a lineal
+    pub fn from_srgb(color: Color) -> Radiance {
+        Radiance {
+            r: srgb::to_linear(color.r),
+            g: srgb::to_linear(color.g),
+            b: srgb::to_linear(color.b),
+        }
+    }
+
+    // Valor de pantalla lineal en [0, 1] a sRGB de 8 bits
+    pub fn to_srgb(self, dither: f32) -> Color {
+        Color {
+            r: srgb::to_srgb(self.r, dither),
+            g: srgb::to_srgb(self.g, dither),
+            b: srgb::to_srgb(self.b, dither),
+        }
+    }
+}
+
+impl Add for Radiance {
+    type Output = Radiance;
+
+    fn add(self, other: Radiance) -> Radiance {
+        Radiance {
+            r: self.r + other.r,
+            g: self.g + other.g,
+            b: self.b + other.b,
+        }
+    }
+}
+
+impl AddAssign for Radiance {
+    fn add_assign(&mut self, other: Radiance) {
+        *self = *self + other;
+    }
+}
+
+impl Mul<f32> for Radiance {
+    type Output = Radiance;
+
+    fn mul(self, scalar: f32) -> Radiance {
+        Radiance {
+            r: self.r * scalar,
+            g: self.g * scalar,
+            b: self.b * scalar,
+        }
+    }
+}
+
+// Producto componente a componente (filtrar una luz por un color)
+impl Mul for Radiance {
+    type Output = Radiance;
+
+    fn mul(self, other: Radiance) -> Radiance {
+        Radiance {
+            r: self.r * other.r,
+            g: self.g * other.g,
+            b: self.b * other.b,
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use nalgebra_glm::Vec3;
+use crate::aabb::Aabb;
 use crate::color::Color;
 use crate::material::Material;
 
@@ -11,6 +12,12 @@ pub struct Intersect {
     pub is_intersecting: bool,
     pub material: Material, 
     pub uv: (f32, f32),
+    // Unidades UV por unidad de distancia sobre la superficie, para saber
+    // cuántos texels cubre un pixel
+    pub uv_density: f32,
+    // Cara de un cubo emisivo alineado a los ejes, de los que el trazado de
+    // caminos muestrea como emisores (`Emitters`)
+    pub emitter: bool,
 }
 
 impl Intersect {
@@ -22,9 +29,21 @@ impl Intersect {
             is_intersecting: true,
             material,
             uv,
+            uv_density: 0.0,
+            emitter: false,
         }
     }
 
+    pub fn with_uv_density(mut self, uv_density: f32) -> Self {
+        self.uv_density = uv_density;
+        self
+    }
+
+    pub fn with_emitter(mut self, emitter: bool) -> Self {
+        self.emitter = emitter;
+        self
+    }
+
     pub fn empty() -> Self {
         Intersect {
             point: Vec3::new(0.0,0.0,0.0),
@@ -40,10 +59,37 @@ impl Intersect {
               0.0,
             ),
             uv: (0.0, 0.0),
+            uv_density: 0.0,
+            emitter: false,
         }
     }
 }
 
-pub trait RayIntersect {
-  fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect;
-}
\ No newline at end of file
+pub trait RayIntersect: Send + Sync {
+  fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3, time: f32) -> Intersect;
+
+  // Caja envolvente del objeto, usada por la BVH
+  fn bounds(&self) -> Aabb;
+
+  // Consulta de oclusión para rayos de sombra: basta con cualquier intersección
+  // antes de `max_distance`, no hace falta la más cercana
+  fn any_hit(&self, ray_origin: &Vec3, ray_direction: &Vec3, max_distance: f32, time: f32) -> bool {
+    let i = self.ray_intersect(ray_origin, ray_direction, time);
+    i.is_intersecting && i.distance < max_distance
+  }
+}
+
+// Permite mezclar distintos tipos de geometría en una misma BVH
+impl<T: RayIntersect + ?Sized> RayIntersect for Box<T> {
+  fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3, time: f32) -> Intersect {
+    (**self).ray_intersect(ray_origin, ray_direction, time)
+  }
+
+  fn bounds(&self) -> Aabb {
+    (**self).bounds()
+  }
+
+  fn any_hit(&self, ray_origin: &Vec3, ray_direction: &Vec3, max_distance: f32, time: f32) -> bool {
+    (**self).any_hit(ray_origin, ray_direction, max_distance, time)
+  }
+}
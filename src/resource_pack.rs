@@ -0,0 +1,433 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::PathBuf;
+
+use nalgebra_glm::{self as glm, Mat4, Vec3};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::color::Color;
+use crate::cube::{Cube, Face};
+use crate::material::{FaceTexture, Material};
+use crate::structure::{BlockState, ImportError};
+use crate::texture_registry::TextureRegistry;
+
+// Lector de resource packs descomprimidos: `assets/<ns>/blockstates/*.json`
+// elige el modelo según el estado del bloque, y `assets/<ns>/models/*.json`
+// (con herencia por `parent`) define las cajas y las texturas de cada cara.
+
+// Límite de la cadena de `parent` y de variables de textura (`#side` -> `#all` -> ...)
+const MAX_DEPTH: usize = 32;
+
+#[derive(Deserialize)]
+struct BlockStateFile {
+    #[serde(default)]
+    variants: Option<BTreeMap<String, OneOrMany<VariantDef>>>,
+    #[serde(default)]
+    multipart: Option<Vec<MultipartCase>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> OneOrMany<T> {
+    // De una lista con pesos se usa siempre la primera variante
+    fn first(&self) -> Option<&T> {
+        match self {
+            OneOrMany::One(value) => Some(value),
+            OneOrMany::Many(values) => values.first(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+struct VariantDef {
+    model: String,
+    // Rotación del modelo en pasos de 90 grados
+    #[serde(default)]
+    x: i32,
+    #[serde(default)]
+    y: i32,
+    // Mantiene las texturas alineadas al mundo al rotar
+    #[serde(default)]
+    uvlock: bool,
+}
+
+#[derive(Deserialize)]
+struct MultipartCase {
+    #[serde(default)]
+    when: Option<Value>,
+    apply: OneOrMany<VariantDef>,
+}
+
+#[derive(Deserialize)]
+struct ModelFile {
+    #[serde(default)]
+    parent: Option<String>,
+    #[serde(default)]
+    textures: HashMap<String, String>,
+    #[serde(default)]
+    elements: Option<Vec<ElementDef>>,
+}
+
+#[derive(Deserialize, Clone)]
+struct ElementDef {
+    from: [f32; 3],
+    to: [f32; 3],
+    #[serde(default)]
+    rotation: Option<ElementRotation>,
+    #[serde(default)]
+    faces: HashMap<String, ModelFaceDef>,
+}
+
+// Rotación de un elemento alrededor de `origin` (en pixeles), de ±22.5 o ±45 grados.
+// Con `rescale` el elemento se estira para seguir ocupando el ancho del bloque.
+#[derive(Deserialize, Clone)]
+struct ElementRotation {
+    origin: [f32; 3],
+    axis: String,
+    angle: f32,
+    #[serde(default)]
+    rescale: bool,
+}
+
+#[derive(Deserialize, Clone)]
+struct ModelFaceDef {
+    #[serde(default)]
+    uv: Option<[f32; 4]>,
+    texture: String,
+    #[serde(default)]
+    rotation: u16,
+}
+
+// Modelo con la herencia ya resuelta
+#[derive(Clone)]
+struct ResolvedModel {
+    textures: HashMap<String, String>,
+    elements: Vec<ElementDef>,
+}
+
+pub struct ResourcePack {
+    roots: Vec<PathBuf>,
+    blockstates: HashMap<String, Option<BlockStateFile>>,
+    models: HashMap<String, ResolvedModel>,
+    // Cubos ya construidos por estado de bloque, en coordenadas de bloque [0, 1]
+    baked: BTreeMap<BlockState, Vec<ModelCube>>,
+}
+
+// Elemento de un modelo. Los elementos rotados llevan la transformación que
+// los coloca en el bloque, en coordenadas de bloque
+pub struct ModelCube {
+    pub cube: Cube,
+    pub transform: Option<Mat4>,
+}
+
+impl ResourcePack {
+    // Varios packs se consultan en orden; el primero que tenga el archivo gana
+    pub fn new(roots: Vec<PathBuf>) -> ResourcePack {
+        ResourcePack {
+            roots,
+            blockstates: HashMap::new(),
+            models: HashMap::new(),
+            baked: BTreeMap::new(),
+        }
+    }
+
+    // Cubos del bloque en coordenadas de bloque [0, 1], con los parámetros de
+    // sombreado de `base` y las texturas del modelo. None si el pack no define
+    // el bloque o su modelo no tiene geometría (fluidos, entidades).
+    pub fn block_cubes(
+        &mut self,
+        state: &BlockState,
+        base: &Material,
+        textures: &mut TextureRegistry,
+    ) -> Result<Option<&[ModelCube]>, ImportError> {
+        if !self.baked.contains_key(state) {
+            let cubes = self.bake(state, base, textures)?;
+            self.baked.insert(state.clone(), cubes);
+        }
+        let cubes = &self.baked[state];
+        Ok(if cubes.is_empty() { None } else { Some(cubes) })
+    }
+
+    fn bake(
+        &mut self,
+        state: &BlockState,
+        base: &Material,
+        textures: &mut TextureRegistry,
+    ) -> Result<Vec<ModelCube>, ImportError> {
+        let mut cubes = Vec::new();
+        for variant in self.variants(state)? {
+            let model = self.model(&variant.model, 0)?;
+            for element in &model.elements {
+                cubes.push(bake_element(element, &model, &variant, base, textures).map_err(|e| {
+                    ImportError::Format(format!("model `{}`: {}", variant.model, e))
+                })?);
+            }
+        }
+        Ok(cubes)
+    }
+
+    // Variantes que aplican al estado (varias en el caso de multipart)
+    fn variants(&mut self, state: &BlockState) -> Result<Vec<VariantDef>, ImportError> {
+        if !self.blockstates.contains_key(&state.name) {
+            let (namespace, block) = split_id(&state.name);
+            let file = self.read_json(namespace, &format!("blockstates/{}.json", block))?;
+            self.blockstates.insert(state.name.clone(), file);
+        }
+        let Some(file) = &self.blockstates[&state.name] else {
+            return Ok(Vec::new());
+        };
+
+        let mut variants = Vec::new();
+        if let Some(cases) = &file.variants {
+            // La primera variante cuyas condiciones se cumplen
+            if let Some(variant) = cases
+                .iter()
+                .find(|(key, _)| variant_matches(key, state))
+                .and_then(|(_, variant)| variant.first())
+            {
+                variants.push(variant.clone());
+            }
+        }
+        if let Some(cases) = &file.multipart {
+            for case in cases {
+                if case.when.as_ref().is_none_or(|when| condition_matches(when, state)) {
+                    variants.extend(case.apply.first().cloned());
+                }
+            }
+        }
+        Ok(variants)
+    }
+
+    fn model(&mut self, name: &str, depth: usize) -> Result<ResolvedModel, ImportError> {
+        if let Some(model) = self.models.get(name) {
+            return Ok(model.clone());
+        }
+        if depth > MAX_DEPTH {
+            return Err(ImportError::Format(format!("model `{}`: parent chain is too deep", name)));
+        }
+
+        let (namespace, path) = split_id(name);
+        let file: ModelFile = self
+            .read_json(namespace, &format!("models/{}.json", path))?
+            .ok_or_else(|| ImportError::Format(format!("model `{}` not found in the resource packs", name)))?;
+
+        // Los modelos "builtin/..." no tienen archivo ni geometría
+        let mut model = match &file.parent {
+            Some(parent) if !split_id(parent).1.starts_with("builtin/") => self.model(parent, depth + 1)?,
+            _ => ResolvedModel {
+                textures: HashMap::new(),
+                elements: Vec::new(),
+            },
+        };
+        // El hijo reemplaza las texturas del padre, y sus elementos a todos los del padre
+        model.textures.extend(file.textures);
+        if let Some(elements) = file.elements {
+            model.elements = elements;
+        }
+
+        self.models.insert(name.to_string(), model.clone());
+        Ok(model)
+    }
+
+    fn read_json<T: for<'de> Deserialize<'de>>(&self, namespace: &str, path: &str) -> Result<Option<T>, ImportError> {
+        for root in &self.roots {
+            let file = root.join("assets").join(namespace).join(path);
+            let source = match fs::read_to_string(&file) {
+                Ok(source) => source,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(ImportError::Io(e)),
+            };
+            return serde_json::from_str(&source)
+                .map(Some)
+                .map_err(|e| ImportError::Format(format!("{}: {}", file.display(), e)));
+        }
+        Ok(None)
+    }
+}
+
+// "minecraft:block/stone" -> ("minecraft", "block/stone"); sin namespace se asume minecraft
+fn split_id(id: &str) -> (&str, &str) {
+    id.split_once(':').unwrap_or(("minecraft", id))
+}
+
+// Clave de `variants`: "" o "facing=north,half=bottom"
+fn variant_matches(key: &str, state: &BlockState) -> bool {
+    key.split(',')
+        .filter(|pair| !pair.is_empty() && *pair != "normal")
+        .all(|pair| match pair.split_once('=') {
+            Some((name, value)) => state.properties.get(name).is_some_and(|v| v == value),
+            None => false,
+        })
+}
+
+// Condición `when` de multipart: propiedades con alternativas "a|b", u "OR"/"AND"
+fn condition_matches(when: &Value, state: &BlockState) -> bool {
+    let Some(conditions) = when.as_object() else {
+        return false;
+    };
+    conditions.iter().all(|(key, value)| match key.as_str() {
+        "OR" => value.as_array().is_some_and(|cases| cases.iter().any(|case| condition_matches(case, state))),
+        "AND" => value.as_array().is_some_and(|cases| cases.iter().all(|case| condition_matches(case, state))),
+        _ => {
+            let expected = match value {
+                Value::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+            let actual = state.properties.get(key);
+            expected.split('|').any(|option| actual.is_some_and(|v| v == option))
+        }
+    })
+}
+
+// Sigue las variables de textura ("#side") hasta un nombre de textura
+fn resolve_texture<'a>(model: &'a ResolvedModel, mut name: &'a str) -> Result<&'a str, String> {
+    for _ in 0..MAX_DEPTH {
+        match name.strip_prefix('#') {
+            Some(variable) => {
+                name = model
+                    .textures
+                    .get(variable)
+                    .ok_or_else(|| format!("texture variable `#{}` is not defined", variable))?;
+            }
+            None => return Ok(name),
+        }
+    }
+    Err(format!("texture variable `{}` does not resolve", name))
+}
+
+fn bake_element(
+    element: &ElementDef,
+    model: &ResolvedModel,
+    variant: &VariantDef,
+    base: &Material,
+    textures: &mut TextureRegistry,
+) -> Result<ModelCube, String> {
+    let from = Vec3::new(element.from[0], element.from[1], element.from[2]) / 16.0;
+    let to = Vec3::new(element.to[0], element.to[1], element.to[2]) / 16.0;
+
+    // Rotación de la variante alrededor del centro del bloque
+    let center = Vec3::new(0.5, 0.5, 0.5);
+    let a = rotate(from - center, variant.x, variant.y) + center;
+    let b = rotate(to - center, variant.x, variant.y) + center;
+    let (min, max) = (a.inf(&b), a.sup(&b));
+
+    let mut material = base.clone();
+    for (key, face_def) in &element.faces {
+        let face = Face::parse(key).ok_or_else(|| format!("unknown face `{}`", key))?;
+        let name = resolve_texture(model, &face_def.texture)?;
+        let texture = textures.load(name).map_err(|e| e.to_string())?;
+
+        let rotated = Face::from_normal(&rotate(face.normal(), variant.x, variant.y));
+        let uv = match face_def.uv {
+            Some(uv) => uv,
+            // Con uvlock la textura queda fija al mundo: el recorte sale de la caja ya rotada
+            None if variant.uvlock => default_uv(rotated, (min * 16.0).into(), (max * 16.0).into()),
+            None => default_uv(face, element.from, element.to),
+        };
+        // Sin uvlock la textura gira con el bloque
+        let extra = if variant.uvlock { 0 } else { texture_rotation(face, rotated, variant.x, variant.y) };
+
+        // Las caras que el modelo no define usan la textura de la primera
+        if material.texture.is_none() {
+            material.texture = Some(texture.clone());
+        }
+        material = material.with_face_texture(
+            rotated,
+            FaceTexture {
+                texture,
+                rotation: (face_def.rotation + extra) % 360,
+                uv: uv.map(|v| v / 16.0),
+            },
+        );
+    }
+    if material.texture.is_none() {
+        material.diffuse = Color::black();
+    }
+
+    let transform = match &element.rotation {
+        Some(rotation) if rotation.angle != 0.0 => Some(element_transform(rotation, variant)?),
+        _ => None,
+    };
+    Ok(ModelCube {
+        cube: Cube { min, max, material },
+        transform,
+    })
+}
+
+// Rotación del elemento como matriz en coordenadas de bloque. El cubo ya está
+// girado por la variante, así que el eje y el origen se giran igual.
+fn element_transform(rotation: &ElementRotation, variant: &VariantDef) -> Result<Mat4, String> {
+    let axis = match rotation.axis.as_str() {
+        "x" => Vec3::new(1.0, 0.0, 0.0),
+        "y" => Vec3::new(0.0, 1.0, 0.0),
+        "z" => Vec3::new(0.0, 0.0, 1.0),
+        other => return Err(format!("unknown rotation axis `{}`", other)),
+    };
+    let center = Vec3::new(0.5, 0.5, 0.5);
+    let origin = Vec3::new(rotation.origin[0], rotation.origin[1], rotation.origin[2]) / 16.0;
+    let origin = rotate(origin - center, variant.x, variant.y) + center;
+    let axis = rotate(axis, variant.x, variant.y);
+
+    let angle = rotation.angle.to_radians();
+    // `rescale` estira los dos ejes perpendiculares al de rotación
+    let scale = if rotation.rescale {
+        let stretch = 1.0 / angle.cos();
+        axis.map(|a| if a.abs() > 0.5 { 1.0 } else { stretch })
+    } else {
+        Vec3::new(1.0, 1.0, 1.0)
+    };
+
+    let transform = glm::translation(&origin)
+        * glm::scaling(&scale)
+        * glm::rotation(angle, &axis)
+        * glm::translation(&-origin);
+    Ok(transform)
+}
+
+// UV por defecto de Minecraft: la parte de la textura que queda "detrás" de la cara
+fn default_uv(face: Face, from: [f32; 3], to: [f32; 3]) -> [f32; 4] {
+    match face {
+        Face::Down => [from[0], 16.0 - to[2], to[0], 16.0 - from[2]],
+        Face::Up => [from[0], from[2], to[0], to[2]],
+        Face::North => [16.0 - to[0], 16.0 - to[1], 16.0 - from[0], 16.0 - from[1]],
+        Face::South => [from[0], 16.0 - to[1], to[0], 16.0 - from[1]],
+        Face::West => [from[2], 16.0 - to[1], to[2], 16.0 - from[1]],
+        Face::East => [16.0 - to[2], 16.0 - to[1], 16.0 - from[2], 16.0 - from[1]],
+    }
+}
+
+// Rotación de la variante: primero `x` y luego `y`, en pasos de 90 grados
+// (y = 90 lleva el norte al este, x = 90 lleva arriba al norte)
+fn rotate(v: Vec3, x: i32, y: i32) -> Vec3 {
+    let mut v = v;
+    for _ in 0..x.rem_euclid(360) / 90 {
+        v = Vec3::new(v.x, v.z, -v.y);
+    }
+    for _ in 0..y.rem_euclid(360) / 90 {
+        v = Vec3::new(-v.z, v.y, v.x);
+    }
+    v
+}
+
+// Cuánto gira la textura de `face` al quedar en `rotated`: se compara hacia
+// dónde apunta el "arriba" de la textura con el "arriba" propio de la cara nueva
+fn texture_rotation(face: Face, rotated: Face, x: i32, y: i32) -> u16 {
+    let (_, up) = face.uv_axes();
+    let up = rotate(up, x, y);
+    let (right, new_up) = rotated.uv_axes();
+    if up.dot(&new_up) > 0.5 {
+        0
+    } else if up.dot(&right) > 0.5 {
+        90
+    } else if up.dot(&new_up) < -0.5 {
+        180
+    } else {
+        270
+    }
+}
@@ -0,0 +1,99 @@
+// Cómo se lee una textura: filtrado y qué pasa con las UV fuera de [0, 1]
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Filter {
+    // Texel más cercano, sin mipmaps: pixel art nítido
+    #[default]
+    Nearest,
+    // Mezcla de los 4 texels vecinos del nivel base
+    Bilinear,
+    // Bilineal en los dos mipmaps más cercanos al tamaño del pixel, mezclados
+    Trilinear,
+}
+
+impl Filter {
+    pub fn parse(name: &str) -> Option<Filter> {
+        match name {
+            "nearest" => Some(Filter::Nearest),
+            "bilinear" => Some(Filter::Bilinear),
+            "trilinear" => Some(Filter::Trilinear),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Wrap {
+    // La textura se repite (mosaico)
+    #[default]
+    Repeat,
+    // Se extiende el texel del borde
+    Clamp,
+    // Se repite reflejada, sin costuras
+    Mirror,
+}
+
+impl Wrap {
+    pub fn parse(name: &str) -> Option<Wrap> {
+        match name {
+            "repeat" => Some(Wrap::Repeat),
+            "clamp" => Some(Wrap::Clamp),
+            "mirror" => Some(Wrap::Mirror),
+            _ => None,
+        }
+    }
+
+    // Lleva un índice de texel cualquiera a [0, size)
+    pub fn texel(self, index: i64, size: usize) -> usize {
+        let size = size as i64;
+        let index = match self {
+            Wrap::Repeat => index.rem_euclid(size),
+            Wrap::Clamp => index.clamp(0, size - 1),
+            Wrap::Mirror => {
+                let period = index.rem_euclid(2 * size);
+                if period < size { period } else { 2 * size - 1 - period }
+            }
+        };
+        index as usize
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Sampler {
+    pub filter: Filter,
+    pub wrap: Wrap,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texels(wrap: Wrap, indices: std::ops::Range<i64>, size: usize) -> Vec<usize> {
+        indices.map(|index| wrap.texel(index, size)).collect()
+    }
+
+    #[test]
+    fn repeat_tiles_in_both_directions() {
+        assert_eq!(texels(Wrap::Repeat, -5..9, 4), [3, 0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3, 0]);
+        assert_eq!(Wrap::Repeat.texel(-4_000_001, 16), 15);
+    }
+
+    #[test]
+    fn clamp_extends_the_edges() {
+        assert_eq!(texels(Wrap::Clamp, -3..7, 4), [0, 0, 0, 0, 1, 2, 3, 3, 3, 3]);
+        assert_eq!(Wrap::Clamp.texel(i64::MIN, 4), 0);
+        assert_eq!(Wrap::Clamp.texel(i64::MAX, 4), 3);
+    }
+
+    #[test]
+    fn mirror_repeats_each_edge_texel_twice() {
+        assert_eq!(texels(Wrap::Mirror, -5..12, 4), [3, 3, 2, 1, 0, 0, 1, 2, 3, 3, 2, 1, 0, 0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn single_texel_textures_always_read_texel_zero() {
+        for wrap in [Wrap::Repeat, Wrap::Clamp, Wrap::Mirror] {
+            assert_eq!(texels(wrap, -3..3, 1), [0; 6]);
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use nalgebra_glm::Vec3;
+
+// Números pseudoaleatorios deterministas: el mismo punto da siempre el mismo
+// ruido, así dos renders de la misma escena son idénticos
+
+// Mezcla de bits (hash de Wang/PCG) de un entero
+pub fn hash(value: u32) -> u32 {
+    let state = value.wrapping_mul(747796405).wrapping_add(2891336453);
+    let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+    (word >> 22) ^ word
+}
+
+// Número en [0, 1) a partir de un hash
+pub fn unit(value: u32) -> f32 {
+    (hash(value) >> 8) as f32 / (1u32 << 24) as f32
+}
+
+// Par de números en [0, 1) para la muestra `index` en un punto de la escena
+pub fn jitter(point: &Vec3, index: u32) -> (f32, f32) {
+    let seed = hash(point.x.to_bits() ^ hash(point.y.to_bits() ^ hash(point.z.to_bits())));
+    let seed = hash(seed ^ index);
+    (unit(seed), unit(seed ^ 0x9e3779b9))
+}
+
+// Celda (s, t) en [0, 1)² de la muestra `index` de `count`, estratificada en
+// una grilla casi cuadrada y desplazada al azar dentro de su celda
+pub fn stratified(index: u32, count: u32, (jx, jy): (f32, f32)) -> (f32, f32) {
+    let columns = (count as f32).sqrt().ceil().max(1.0) as u32;
+    let rows = count.div_ceil(columns);
+    let s = ((index % columns) as f32 + jx) / columns as f32;
+    let t = ((index / columns) as f32 + jy) / rows as f32;
+    (s, t)
+}
+
+// Generador pseudoaleatorio pequeño (PCG) para los caminos de luz: cada pixel
+// y cada pasada lo siembran distinto, así las pasadas no se repiten
+pub struct Rng {
+    state: u32,
+}
+
+impl Rng {
+    pub fn new(seed: u32) -> Rng {
+        Rng { state: hash(seed) }
+    }
+
+    // Número en [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        self.state = self.state.wrapping_mul(747796405).wrapping_add(2891336453);
+        unit(self.state)
+    }
+}
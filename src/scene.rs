@@ -0,0 +1,1007 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use nalgebra_glm::{self as glm, Mat4, Vec3};
+use serde::Deserialize;
+
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::cube::{Cube, Face};
+use crate::instance::Instance;
+use crate::anvil::{AnvilWorld, Dimension};
+use crate::light::{self, Attenuation, Light, LightKind, LightShape};
+use crate::mesh::CrossedQuads;
+use crate::material::{self, FaceTexture, Material, ShadingModel};
+use crate::obj;
+use crate::pathtrace::Emitters;
+use crate::ray_intersect::RayIntersect;
+use crate::resource_pack::ResourcePack;
+use crate::sampler::{Filter, Sampler, Wrap};
+use crate::structure::{self, BlockMapper, ImportReport, Structure};
+use crate::texture_registry::TextureRegistry;
+use crate::voxel::VoxelWorld;
+
+// Escena ya construida, lista para renderizar
+pub struct Scene {
+    pub objects: Vec<Box<dyn RayIntersect>>,
+    pub lights: Vec<Light>,
+    pub daylight: Light,
+    // Caras de los cubos emisivos, para el trazado de caminos
+    pub emitters: Emitters,
+    pub camera: Camera,
+    // Resumen de cada estructura o región importada, con su ruta
+    pub imports: Vec<(String, ImportReport)>,
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io { path: String, source: std::io::Error },
+    Parse { path: String, source: serde_json::Error },
+    Invalid { path: String, field: String, message: String },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io { path, source } => write!(f, "{}: cannot read scene file: {}", path, source),
+            // serde_json ya incluye "at line X column Y" en su mensaje
+            SceneError::Parse { path, source } => write!(f, "{}: {}", path, source),
+            SceneError::Invalid { path, field, message } => write!(f, "{}: `{}`: {}", path, field, message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+// Formato del archivo de escena (JSON)
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneFile {
+    camera: CameraDef,
+    daylight: LightDef,
+    #[serde(default)]
+    lights: Vec<LightDef>,
+    // Carpetas (o resource packs descomprimidos) donde se buscan las texturas por nombre
+    #[serde(default = "default_texture_paths")]
+    texture_paths: Vec<String>,
+    // Resource packs descomprimidos con modelos para los bloques importados
+    #[serde(default)]
+    resource_packs: Vec<String>,
+    #[serde(default)]
+    materials: HashMap<String, MaterialDef>,
+    #[serde(default)]
+    cubes: Vec<CubeDef>,
+    #[serde(default)]
+    structures: Vec<StructureDef>,
+    #[serde(default)]
+    regions: Vec<RegionDef>,
+    // Modelos OBJ (mobs, objetos exportados de Blockbench)
+    #[serde(default)]
+    models: Vec<ModelDef>,
+    // Si existe, los cubos alineados a esta rejilla se guardan en un VoxelWorld
+    #[serde(default)]
+    grid: Option<GridDef>,
+    // Si existe, los bloques emisivos también iluminan la escena
+    #[serde(default)]
+    emissive_lights: Option<EmissiveLightsDef>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EmissiveLightsDef {
+    #[serde(default = "default_cluster_size")]
+    cluster_size: f32,
+    #[serde(default = "default_intensity")]
+    intensity: f32,
+    // Si existe, cada grupo es una luz con la forma de sus bloques, con esta
+    // cantidad de rayos de sombra
+    #[serde(default)]
+    block_samples: Option<u32>,
+    #[serde(default)]
+    attenuation: Option<AttenuationDef>,
+    #[serde(default)]
+    range: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GridDef {
+    cell_size: f32,
+    #[serde(default)]
+    origin: [f32; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDef {
+    eye: [f32; 3],
+    center: [f32; 3],
+    #[serde(default = "default_up")]
+    up: [f32; 3],
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightDef {
+    // "point", "spot" o "directional"; el sol es direccional por defecto
+    #[serde(default, rename = "type")]
+    kind: Option<String>,
+    // Las luces direccionales no la usan
+    #[serde(default)]
+    position: Option<[f32; 3]>,
+    // Hacia donde apuntan los focos y viaja la luz direccional
+    #[serde(default)]
+    direction: Option<[f32; 3]>,
+    // Semiángulo del cono de los focos, en grados
+    #[serde(default)]
+    angle: Option<f32>,
+    // Fracción del cono que se desvanece hacia el borde
+    #[serde(default = "default_spot_falloff")]
+    falloff: f32,
+    // Radio aparente del disco de la luz direccional, en grados
+    #[serde(default)]
+    angular_radius: f32,
+    color: [i32; 3],
+    intensity: f32,
+    #[serde(default)]
+    day_angle: f32,
+    #[serde(default)]
+    shape: Option<LightShapeDef>,
+    // Rayos de sombra por punto para las luces con superficie
+    #[serde(default = "default_light_samples")]
+    samples: u32,
+    #[serde(default)]
+    attenuation: Option<AttenuationDef>,
+    // Distancia máxima a la que ilumina
+    #[serde(default)]
+    range: Option<f32>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum AttenuationDef {
+    None,
+    InverseSquare {
+        // Distancia con intensidad completa
+        #[serde(default = "default_reference_distance")]
+        reference: f32,
+    },
+    // Nivel de luz de Minecraft: llega a 0 a los 15 bloques
+    Minecraft {
+        #[serde(default = "default_block_size")]
+        block_size: f32,
+    },
+    Linear { range: f32 },
+    // Puntos [distancia, factor]
+    Curve { points: Vec<[f32; 2]> },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+enum LightShapeDef {
+    Point,
+    Sphere { radius: f32 },
+    // Lados del rectángulo, centrado en `position`
+    Rectangle { u: [f32; 3], v: [f32; 3] },
+    // Tamaño de la caja, centrada en `position`
+    Block {
+        #[serde(default = "default_scale")]
+        size: [f32; 3],
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MaterialDef {
+    // Nombre o ruta de la textura, resuelto por `TextureRegistry`
+    #[serde(default)]
+    texture: Option<String>,
+    #[serde(default)]
+    diffuse: Option<[i32; 3]>,
+    // Texturas por cara: "north", "south", "east", "west", "up"/"top",
+    // "down"/"bottom", o los grupos "side" (las cuatro laterales) y "all"
+    #[serde(default)]
+    faces: HashMap<String, FaceDef>,
+    spec: f32,
+    albedo: [f32; 2],
+    #[serde(default)]
+    reflectivity: f32,
+    #[serde(default)]
+    transparency: f32,
+    #[serde(default)]
+    refraction_index: f32,
+    #[serde(default)]
+    emission: Option<[i32; 3]>,
+    #[serde(default = "default_intensity")]
+    emission_strength: f32,
+    // Textura usada como máscara de la emisión
+    #[serde(default)]
+    emission_texture: Option<String>,
+    // Los texels con menos opacidad se descartan (hojas, plantas, rejas)
+    #[serde(default)]
+    alpha_cutoff: Option<f32>,
+    // Filtrado de las texturas: "nearest", "bilinear" o "trilinear"
+    #[serde(default)]
+    filter: Option<String>,
+    // UV fuera de [0, 1]: "repeat", "clamp" o "mirror"
+    #[serde(default)]
+    wrap: Option<String>,
+    // Relieve: mapa de normales (RGB, estilo OpenGL) y de alturas (gris,
+    // blanco arriba), con su profundidad en fracciones de la textura
+    #[serde(default)]
+    normal_map: Option<String>,
+    #[serde(default)]
+    height_map: Option<String>,
+    #[serde(default = "default_relief_depth")]
+    relief_depth: f32,
+    // Modelo de sombreado: "phong" (por defecto) o "pbr"
+    #[serde(default)]
+    shading: Option<String>,
+    // Parámetros PBR. Sin `roughness` se usa la equivalente a `spec`
+    #[serde(default)]
+    roughness: Option<f32>,
+    #[serde(default)]
+    metalness: f32,
+    #[serde(default = "default_f0")]
+    f0: f32,
+    // Mapa especular con el formato `_s` de LabPBR
+    #[serde(default)]
+    specular_map: Option<String>,
+}
+
+// Textura de una cara: solo el nombre, o el nombre con rotación y recorte
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FaceDef {
+    Texture(String),
+    Rotated(RotatedFaceDef),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RotatedFaceDef {
+    texture: String,
+    #[serde(default)]
+    rotation: u16,
+    // Recorte [u1, v1, u2, v2] en pixeles de una textura de 16x16
+    #[serde(default)]
+    uv: Option<[f32; 4]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CubeDef {
+    min: [f32; 3],
+    max: [f32; 3],
+    material: String,
+    // Repite el cubo [x, y, z] veces, desplazándolo por su propio tamaño
+    #[serde(default = "default_repeat")]
+    repeat: [u32; 3],
+    // Cubo girado o escalado (marcos, bloques inclinados); no entra en la rejilla
+    #[serde(default)]
+    transform: Option<TransformDef>,
+    // Dos planos cruzados en diagonal en lugar de una caja (plantas, fuego)
+    #[serde(default)]
+    cross: bool,
+}
+
+// Escala, rotación en grados (primero X, luego Y, luego Z) alrededor de
+// `pivot` y traslación. Sin `pivot` se usa el centro del cubo, o el origen
+// en un modelo.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TransformDef {
+    #[serde(default)]
+    translate: [f32; 3],
+    #[serde(default)]
+    rotate: [f32; 3],
+    #[serde(default = "default_scale")]
+    scale: [f32; 3],
+    #[serde(default)]
+    pivot: Option<[f32; 3]>,
+}
+
+impl TransformDef {
+    fn matrix(&self, center: Vec3) -> Mat4 {
+        let pivot = self.pivot.map_or(center, vec3);
+        let rotation = glm::rotation(self.rotate[2].to_radians(), &Vec3::z())
+            * glm::rotation(self.rotate[1].to_radians(), &Vec3::y())
+            * glm::rotation(self.rotate[0].to_radians(), &Vec3::x());
+        glm::translation(&(vec3(self.translate) + pivot))
+            * rotation
+            * glm::scaling(&vec3(self.scale))
+            * glm::translation(&-pivot)
+    }
+}
+
+// Estructura de Minecraft importada desde un archivo .nbt o .schem
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StructureDef {
+    path: String,
+    #[serde(default)]
+    offset: [f32; 3],
+    #[serde(default = "default_block_size")]
+    block_size: f32,
+    // ID de bloque -> nombre de material de la escena
+    #[serde(default)]
+    blocks: HashMap<String, String>,
+    // Material para los bloques sin correspondencia
+    #[serde(default)]
+    fallback: Option<String>,
+}
+
+// Caja recortada de un mundo guardado (carpeta de guardado con archivos .mca)
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RegionDef {
+    world: String,
+    #[serde(default = "default_dimension")]
+    dimension: String,
+    // Esquinas inclusivas en coordenadas de bloque del mundo
+    min: [i32; 3],
+    max: [i32; 3],
+    #[serde(default)]
+    offset: [f32; 3],
+    #[serde(default = "default_block_size")]
+    block_size: f32,
+    #[serde(default)]
+    blocks: HashMap<String, String>,
+    #[serde(default)]
+    fallback: Option<String>,
+}
+
+// Malla Wavefront OBJ; los materiales salen de sus archivos MTL salvo que se
+// indique uno de la escena
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ModelDef {
+    path: String,
+    #[serde(default)]
+    material: Option<String>,
+    #[serde(default)]
+    transform: Option<TransformDef>,
+}
+
+fn default_dimension() -> String {
+    "overworld".to_string()
+}
+
+fn default_block_size() -> f32 {
+    0.5
+}
+
+fn default_texture_paths() -> Vec<String> {
+    vec!["./assets".to_string()]
+}
+
+fn default_cluster_size() -> f32 {
+    1.0
+}
+
+fn default_intensity() -> f32 {
+    1.0
+}
+
+fn default_reference_distance() -> f32 {
+    1.0
+}
+
+fn default_spot_falloff() -> f32 {
+    0.2
+}
+
+fn default_light_samples() -> u32 {
+    16
+}
+
+fn default_up() -> [f32; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_repeat() -> [u32; 3] {
+    [1, 1, 1]
+}
+
+fn default_relief_depth() -> f32 {
+    material::DEFAULT_RELIEF_DEPTH
+}
+
+fn default_f0() -> f32 {
+    material::DEFAULT_F0
+}
+
+fn default_scale() -> [f32; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn vec3(v: [f32; 3]) -> Vec3 {
+    Vec3::new(v[0], v[1], v[2])
+}
+
+fn color(c: [i32; 3]) -> Color {
+    Color::new(c[0], c[1], c[2])
+}
+
+// Traducción de bloques a materiales para una estructura o región importada.
+// Los errores traen el campo inválido.
+fn block_mapper<'a>(
+    field: &str,
+    materials: &'a HashMap<&str, Material>,
+    builtin: &'a HashMap<&'static str, Material>,
+    textures: &'a mut TextureRegistry,
+    pack: Option<&'a mut ResourcePack>,
+    blocks: &HashMap<String, String>,
+    fallback: Option<&String>,
+) -> Result<BlockMapper<'a>, (String, String)> {
+    let lookup = |field: String, name: &String| {
+        materials.get(name.as_str()).ok_or_else(|| (field, format!("unknown material `{}`", name)))
+    };
+
+    // Los materiales de la escena se pueden usar por nombre de bloque ("obsidian")
+    let mut mapping: HashMap<String, &Material> =
+        materials.iter().map(|(name, material)| (name.to_string(), material)).collect();
+    for (block, name) in blocks {
+        mapping.insert(block.clone(), lookup(format!("{}.blocks.{}", field, block), name)?);
+    }
+    let fallback = match fallback {
+        Some(name) => lookup(format!("{}.fallback", field), name)?.clone(),
+        None => structure::unknown_block_material(),
+    };
+
+    Ok(BlockMapper {
+        materials: mapping,
+        builtin,
+        fallback,
+        pack,
+        textures,
+    })
+}
+
+impl AttenuationDef {
+    fn build(&self) -> Result<Attenuation, (&'static str, String)> {
+        let positive = |field, value: f32| {
+            if value > 0.0 {
+                Ok(value)
+            } else {
+                Err((field, "must be greater than zero".to_string()))
+            }
+        };
+        Ok(match self {
+            AttenuationDef::None => Attenuation::None,
+            AttenuationDef::InverseSquare { reference } => Attenuation::InverseSquare {
+                reference: positive("attenuation.reference", *reference)?,
+            },
+            AttenuationDef::Minecraft { block_size } => Attenuation::Linear {
+                range: 15.0 * positive("attenuation.block_size", *block_size)?,
+            },
+            AttenuationDef::Linear { range } => Attenuation::Linear {
+                range: positive("attenuation.range", *range)?,
+            },
+            AttenuationDef::Curve { points } => {
+                if points.is_empty() {
+                    return Err(("attenuation.points", "must have at least one point".to_string()));
+                }
+                if points.windows(2).any(|pair| pair[1][0] < pair[0][0]) {
+                    return Err(("attenuation.points", "must be sorted by distance".to_string()));
+                }
+                if points.iter().any(|point| point[0] < 0.0 || point[1] < 0.0) {
+                    return Err(("attenuation.points", "must not be negative".to_string()));
+                }
+                Attenuation::Curve(points.iter().map(|point| (point[0], point[1])).collect())
+            }
+        })
+    }
+}
+
+// Atenuación y alcance compartidos por las luces y las luces emisivas
+fn build_falloff(
+    attenuation: &Option<AttenuationDef>,
+    range: Option<f32>,
+) -> Result<(Attenuation, Option<f32>), (&'static str, String)> {
+    if range.is_some_and(|range| range <= 0.0) {
+        return Err(("range", "must be greater than zero".to_string()));
+    }
+    let attenuation = match attenuation {
+        Some(def) => def.build()?,
+        None => Attenuation::None,
+    };
+    Ok((attenuation, range))
+}
+
+impl LightDef {
+    // Los errores traen el campo inválido, relativo a la luz
+    // `default_kind` es el tipo si el archivo no lo indica
+    fn build(&self, default_kind: &str) -> Result<Light, (&'static str, String)> {
+        let kind_name = self.kind.as_deref().unwrap_or(default_kind);
+        let position = match (self.position, kind_name) {
+            (Some(position), _) => vec3(position),
+            (None, "directional") => Vec3::zeros(),
+            (None, _) => return Err(("position", "is required for point and spot lights".to_string())),
+        };
+        let direction = match self.direction.map(vec3) {
+            Some(direction) if direction.magnitude() == 0.0 => {
+                return Err(("direction", "must not be zero".to_string()))
+            }
+            direction => direction.map(|direction| direction.normalize()),
+        };
+        let kind = match kind_name {
+            "point" => LightKind::Point,
+            "directional" => {
+                if !(0.0..90.0).contains(&self.angular_radius) {
+                    return Err(("angular_radius", "must be between 0 and 90 degrees".to_string()));
+                }
+                if self.shape.is_some() {
+                    return Err(("shape", "is not used by directional lights, use `angular_radius`".to_string()));
+                }
+                // La luz llega desde el infinito, no se atenúa con la distancia
+                if self.attenuation.is_some() {
+                    return Err(("attenuation", "is not used by directional lights".to_string()));
+                }
+                if self.range.is_some() {
+                    return Err(("range", "is not used by directional lights".to_string()));
+                }
+                LightKind::Directional {
+                    // El sol la recalcula según `day_angle`; por defecto cae vertical
+                    direction: direction.unwrap_or(-Vec3::y()),
+                    angular_radius: self.angular_radius.to_radians(),
+                }
+            }
+            "spot" => {
+                let direction = direction.ok_or(("direction", "is required for spot lights".to_string()))?;
+                let angle = self.angle.ok_or(("angle", "is required for spot lights".to_string()))?;
+                if !(angle > 0.0 && angle <= 90.0) {
+                    return Err(("angle", "must be greater than 0 and at most 90 degrees".to_string()));
+                }
+                if !(0.0..=1.0).contains(&self.falloff) {
+                    return Err(("falloff", "must be between 0 and 1".to_string()));
+                }
+                LightKind::Spot { direction, angle: angle.to_radians(), falloff: self.falloff }
+            }
+            other => return Err(("type", format!("unknown light type `{}`, expected point, spot or directional", other))),
+        };
+        if self.samples == 0 {
+            return Err(("samples", "must be at least 1".to_string()));
+        }
+        let shape = match &self.shape {
+            None | Some(LightShapeDef::Point) => LightShape::Point,
+            Some(LightShapeDef::Sphere { radius }) => {
+                if *radius <= 0.0 {
+                    return Err(("shape.radius", "must be greater than zero".to_string()));
+                }
+                LightShape::Sphere { radius: *radius }
+            }
+            Some(LightShapeDef::Rectangle { u, v }) => LightShape::Rectangle { u: vec3(*u), v: vec3(*v) },
+            Some(LightShapeDef::Block { size }) => {
+                if size.iter().any(|&side| side <= 0.0) {
+                    return Err(("shape.size", "must be greater than zero on every axis".to_string()));
+                }
+                LightShape::Block { half_extents: vec3(*size) * 0.5 }
+            }
+        };
+        let (attenuation, range) = build_falloff(&self.attenuation, self.range)?;
+        let mut light = Light::new(position, color(self.color), self.intensity)
+            .with_kind(kind)
+            .with_shape(shape, self.samples)
+            .with_attenuation(attenuation, range);
+        light.day_angle = self.day_angle;
+        Ok(light)
+    }
+}
+
+impl Scene {
+    pub fn load(path: &str) -> Result<Scene, SceneError> {
+        let source = fs::read_to_string(path).map_err(|source| SceneError::Io {
+            path: path.to_string(),
+            source,
+        })?;
+        Scene::parse(path, &source)
+    }
+
+    pub fn parse(path: &str, source: &str) -> Result<Scene, SceneError> {
+        let file: SceneFile = serde_json::from_str(source).map_err(|source| SceneError::Parse {
+            path: path.to_string(),
+            source,
+        })?;
+
+        let invalid = |field: String, message: String| SceneError::Invalid {
+            path: path.to_string(),
+            field,
+            message,
+        };
+
+        // Las texturas de los modelos se buscan también dentro de los resource packs
+        let texture_paths = file.texture_paths.iter().chain(&file.resource_packs).map(PathBuf::from).collect();
+        let mut textures = TextureRegistry::new(texture_paths);
+        let mut pack = (!file.resource_packs.is_empty())
+            .then(|| ResourcePack::new(file.resource_packs.iter().map(PathBuf::from).collect()));
+        // `data` carga mapas de normales y alturas, que no pasan por sRGB
+        let mut load_texture = |field: String, name: &str, data: bool| {
+            let texture = if data { textures.load_data(name) } else { textures.load(name) };
+            texture.map_err(|e| invalid(field, e.to_string()))
+        };
+
+        let mut materials = HashMap::new();
+        for (name, def) in &file.materials {
+            // Los grupos se aplican primero para que una cara concreta los pueda reemplazar
+            let mut face_defs: Vec<_> = def.faces.iter().collect();
+            face_defs.sort_by_key(|(key, _)| match key.as_str() {
+                "all" => 0,
+                "side" => 1,
+                _ => 2,
+            });
+            let mut faces: [Option<FaceTexture>; 6] = Default::default();
+            for (key, face_def) in face_defs {
+                let field = format!("materials.{}.faces.{}", name, key);
+                let targets = match key.as_str() {
+                    "all" => Face::ALL.to_vec(),
+                    "side" => vec![Face::North, Face::South, Face::West, Face::East],
+                    _ => vec![Face::parse(key).ok_or_else(|| {
+                        invalid(
+                            field.clone(),
+                            "unknown face, expected north, south, east, west, up, down, top, bottom, side or all"
+                                .to_string(),
+                        )
+                    })?],
+                };
+                let (texture, rotation, uv) = match face_def {
+                    FaceDef::Texture(texture) => (texture, 0, None),
+                    FaceDef::Rotated(def) => (&def.texture, def.rotation, def.uv),
+                };
+                if !matches!(rotation, 0 | 90 | 180 | 270) {
+                    return Err(invalid(
+                        format!("{}.rotation", field),
+                        "must be 0, 90, 180 or 270".to_string(),
+                    ));
+                }
+                let texture = load_texture(field, texture, false)?;
+                for face in targets {
+                    faces[face.index()] = Some(FaceTexture {
+                        texture: texture.clone(),
+                        rotation,
+                        uv: uv.map_or(FaceTexture::FULL_UV, |uv| uv.map(|v| v / 16.0)),
+                    });
+                }
+            }
+
+            let material = match (&def.texture, def.diffuse) {
+                (Some(texture), None) => Material::new_with_texture(
+                    def.spec,
+                    def.albedo,
+                    def.reflectivity,
+                    def.transparency,
+                    def.refraction_index,
+                    load_texture(format!("materials.{}.texture", name), texture, false)?,
+                ),
+                (None, Some(diffuse)) => Material::new(
+                    color(diffuse),
+                    def.spec,
+                    def.albedo,
+                    def.reflectivity,
+                    def.transparency,
+                    def.refraction_index,
+                ),
+                // Sin textura general, todas las caras deben tener la suya
+                (None, None) if faces.iter().all(Option::is_some) => Material::new(
+                    Color::black(),
+                    def.spec,
+                    def.albedo,
+                    def.reflectivity,
+                    def.transparency,
+                    def.refraction_index,
+                ),
+                _ => {
+                    return Err(invalid(
+                        format!("materials.{}", name),
+                        "expected exactly one of `texture` or `diffuse`, unless `faces` covers every face".to_string(),
+                    ))
+                }
+            };
+            let material = Face::ALL
+                .into_iter()
+                .zip(faces)
+                .filter_map(|(face, texture)| Some((face, texture?)))
+                .fold(material, |material, (face, texture)| material.with_face_texture(face, texture));
+            let material = match def.emission {
+                Some(emission) => {
+                    let mask = match &def.emission_texture {
+                        Some(texture) => Some(load_texture(format!("materials.{}.emission_texture", name), texture, false)?),
+                        None => None,
+                    };
+                    material.with_emission(color(emission), def.emission_strength, mask)
+                }
+                None if def.emission_texture.is_some() => {
+                    return Err(invalid(
+                        format!("materials.{}.emission_texture", name),
+                        "requires an `emission` color".to_string(),
+                    ))
+                }
+                None => material,
+            };
+            let material = match def.alpha_cutoff {
+                Some(cutoff) if !(0.0..=1.0).contains(&cutoff) => {
+                    return Err(invalid(
+                        format!("materials.{}.alpha_cutoff", name),
+                        "must be between 0 and 1".to_string(),
+                    ))
+                }
+                Some(cutoff) => material.with_alpha_cutoff(cutoff),
+                None => material,
+            };
+            let mut sampler = Sampler::default();
+            if let Some(filter) = &def.filter {
+                sampler.filter = Filter::parse(filter).ok_or_else(|| {
+                    invalid(
+                        format!("materials.{}.filter", name),
+                        format!("unknown filter `{}`, expected nearest, bilinear or trilinear", filter),
+                    )
+                })?;
+            }
+            if let Some(wrap) = &def.wrap {
+                sampler.wrap = Wrap::parse(wrap).ok_or_else(|| {
+                    invalid(
+                        format!("materials.{}.wrap", name),
+                        format!("unknown wrap mode `{}`, expected repeat, clamp or mirror", wrap),
+                    )
+                })?;
+            }
+            let material = material.with_sampler(sampler);
+
+            let normal_map = match &def.normal_map {
+                Some(texture) => Some(load_texture(format!("materials.{}.normal_map", name), texture, true)?),
+                None => None,
+            };
+            let height_map = match &def.height_map {
+                Some(texture) => Some(load_texture(format!("materials.{}.height_map", name), texture, true)?),
+                None => None,
+            };
+            if def.relief_depth < 0.0 {
+                return Err(invalid(
+                    format!("materials.{}.relief_depth", name),
+                    "must not be negative".to_string(),
+                ));
+            }
+            let material = material.with_relief(normal_map, height_map, def.relief_depth);
+
+            let model = match &def.shading {
+                Some(shading) => ShadingModel::parse(shading).ok_or_else(|| {
+                    invalid(
+                        format!("materials.{}.shading", name),
+                        format!("unknown shading model `{}`, expected phong or pbr", shading),
+                    )
+                })?,
+                None => ShadingModel::default(),
+            };
+            let roughness = def.roughness.unwrap_or(material.roughness);
+            for (field, value) in [("roughness", roughness), ("metalness", def.metalness), ("f0", def.f0)] {
+                if !(0.0..=1.0).contains(&value) {
+                    return Err(invalid(format!("materials.{}.{}", name, field), "must be between 0 and 1".to_string()));
+                }
+            }
+            let specular_map = match &def.specular_map {
+                Some(texture) => Some(load_texture(format!("materials.{}.specular_map", name), texture, true)?),
+                None => None,
+            };
+            let material = material
+                .with_pbr(roughness, def.metalness, def.f0, specular_map)
+                .with_shading(model);
+            materials.insert(name.as_str(), material);
+        }
+
+        let mut cubes = Vec::new();
+        // Geometría transformada: va directo a la BVH
+        let mut instances: Vec<Box<dyn RayIntersect>> = Vec::new();
+        let mut imports = Vec::new();
+        for (index, def) in file.cubes.iter().enumerate() {
+            let material = materials.get(def.material.as_str()).ok_or_else(|| {
+                invalid(
+                    format!("cubes[{}].material", index),
+                    format!("unknown material `{}`", def.material),
+                )
+            })?;
+
+            let min = vec3(def.min);
+            let max = vec3(def.max);
+            if min.x >= max.x || min.y >= max.y || min.z >= max.z {
+                return Err(invalid(
+                    format!("cubes[{}]", index),
+                    "`min` must be smaller than `max` on every axis".to_string(),
+                ));
+            }
+
+            let size = max - min;
+            for i in 0..def.repeat[0] {
+                for j in 0..def.repeat[1] {
+                    for k in 0..def.repeat[2] {
+                        let offset = Vec3::new(i as f32 * size.x, j as f32 * size.y, k as f32 * size.z);
+                        let cube = Cube {
+                            min: min + offset,
+                            max: max + offset,
+                            material: material.clone(),
+                        };
+                        let center = (cube.min + cube.max) * 0.5;
+                        let object: Box<dyn RayIntersect> = if def.cross {
+                            Box::new(CrossedQuads::new(cube.min, cube.max, cube.material))
+                        } else if def.transform.is_some() {
+                            Box::new(cube)
+                        } else {
+                            cubes.push(cube);
+                            continue;
+                        };
+                        let Some(transform) = &def.transform else {
+                            instances.push(object);
+                            continue;
+                        };
+                        let instance = Instance::new(object, transform.matrix(center)).ok_or_else(|| {
+                            invalid(
+                                format!("cubes[{}].transform.scale", index),
+                                "must not be zero on any axis".to_string(),
+                            )
+                        })?;
+                        instances.push(Box::new(instance));
+                    }
+                }
+            }
+        }
+
+        // Se cargan una sola vez y solo si la escena importa bloques
+        let builtin = if file.structures.is_empty() && file.regions.is_empty() {
+            HashMap::new()
+        } else {
+            structure::builtin_materials(&mut textures)
+                .map_err(|e| invalid("texture_paths".to_string(), e.to_string()))?
+        };
+
+        for (index, def) in file.structures.iter().enumerate() {
+            let field = format!("structures[{}]", index);
+            let mut mapper = block_mapper(
+                &field,
+                &materials,
+                &builtin,
+                &mut textures,
+                pack.as_mut(),
+                &def.blocks,
+                def.fallback.as_ref(),
+            )
+            .map_err(|(field, message)| invalid(field, message))?;
+            let imported = Structure::load(&def.path)
+                .map_err(|e| invalid(format!("{}.path", field), format!("{}: {}", def.path, e)))?;
+            let (blocks, report) = mapper
+                .build_cubes(&imported, vec3(def.offset), def.block_size)
+                .map_err(|e| invalid(field.clone(), e.to_string()))?;
+            imports.push((def.path.clone(), report));
+            cubes.extend(blocks.cubes);
+            instances.extend(blocks.objects);
+        }
+
+        for (index, def) in file.regions.iter().enumerate() {
+            let field = format!("regions[{}]", index);
+            let mut mapper = block_mapper(
+                &field,
+                &materials,
+                &builtin,
+                &mut textures,
+                pack.as_mut(),
+                &def.blocks,
+                def.fallback.as_ref(),
+            )
+            .map_err(|(field, message)| invalid(field, message))?;
+            let dimension = Dimension::parse(&def.dimension).ok_or_else(|| {
+                invalid(
+                    format!("{}.dimension", field),
+                    format!("unknown dimension `{}`, expected overworld, nether or end", def.dimension),
+                )
+            })?;
+            if (0..3).any(|axis| def.min[axis] > def.max[axis]) {
+                return Err(invalid(
+                    field,
+                    "`min` must not be greater than `max` on any axis".to_string(),
+                ));
+            }
+
+            let imported = AnvilWorld::open(&def.world, dimension)
+                .and_then(|mut world| world.read_box(def.min, def.max))
+                .map_err(|e| invalid(format!("{}.world", field), format!("{}: {}", def.world, e)))?;
+            let (blocks, report) = mapper
+                .build_cubes(&imported, vec3(def.offset), def.block_size)
+                .map_err(|e| invalid(field.clone(), e.to_string()))?;
+            imports.push((def.world.clone(), report));
+            cubes.extend(blocks.cubes);
+            instances.extend(blocks.objects);
+        }
+
+        for (index, def) in file.models.iter().enumerate() {
+            let field = format!("models[{}]", index);
+            let material = match &def.material {
+                Some(name) => Some(materials.get(name.as_str()).ok_or_else(|| {
+                    invalid(format!("{}.material", field), format!("unknown material `{}`", name))
+                })?),
+                None => None,
+            };
+            let mesh = obj::load(Path::new(&def.path), &mut textures, material)
+                .map_err(|e| invalid(format!("{}.path", field), e.to_string()))?;
+            match &def.transform {
+                Some(transform) => {
+                    let instance = Instance::new(mesh, transform.matrix(Vec3::zeros())).ok_or_else(|| {
+                        invalid(
+                            format!("{}.transform.scale", field),
+                            "must not be zero on any axis".to_string(),
+                        )
+                    })?;
+                    instances.push(Box::new(instance));
+                }
+                None => instances.push(Box::new(mesh)),
+            }
+        }
+
+        let daylight = file
+            .daylight
+            .build("directional")
+            .map_err(|(field, message)| invalid(format!("daylight.{}", field), message))?;
+        let mut lights = Vec::new();
+        for (index, def) in file.lights.iter().enumerate() {
+            let light = def
+                .build("point")
+                .map_err(|(field, message)| invalid(format!("lights[{}].{}", index, field), message))?;
+            lights.push(light);
+        }
+        if let Some(def) = &file.emissive_lights {
+            if def.cluster_size <= 0.0 {
+                return Err(invalid(
+                    "emissive_lights.cluster_size".to_string(),
+                    "must be greater than zero".to_string(),
+                ));
+            }
+            if def.block_samples == Some(0) {
+                return Err(invalid(
+                    "emissive_lights.block_samples".to_string(),
+                    "must be at least 1".to_string(),
+                ));
+            }
+            let (attenuation, range) = build_falloff(&def.attenuation, def.range)
+                .map_err(|(field, message)| invalid(format!("emissive_lights.{}", field), message))?;
+            lights.extend(
+                light::emissive_lights(&cubes, def.cluster_size, def.intensity, def.block_samples)
+                    .into_iter()
+                    .map(|light| light.with_attenuation(attenuation.clone(), range)),
+            );
+        }
+
+        let emitters = Emitters::from_cubes(&cubes);
+
+        let mut objects: Vec<Box<dyn RayIntersect>> = Vec::new();
+        match &file.grid {
+            Some(grid) => {
+                if grid.cell_size <= 0.0 {
+                    return Err(invalid(
+                        "grid.cell_size".to_string(),
+                        "must be greater than zero".to_string(),
+                    ));
+                }
+                let (world, free) = VoxelWorld::from_cubes(cubes, vec3(grid.origin), grid.cell_size);
+                if !world.is_empty() {
+                    objects.push(Box::new(world));
+                }
+                objects.extend(free.into_iter().map(|cube| Box::new(cube) as Box<dyn RayIntersect>));
+            }
+            None => {
+                objects.extend(cubes.into_iter().map(|cube| Box::new(cube) as Box<dyn RayIntersect>));
+            }
+        }
+        objects.extend(instances);
+
+        let camera = Camera::new(vec3(file.camera.eye), vec3(file.camera.center), vec3(file.camera.up));
+
+        Ok(Scene {
+            objects,
+            lights,
+            daylight,
+            emitters,
+            camera,
+            imports,
+        })
+    }
+}
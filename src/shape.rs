@@ -0,0 +1,165 @@
+use crate::structure::BlockState;
+
+// Formas de los bloques que no ocupan el bloque completo (losas, escaleras,
+// vallas, paneles, muros, antorchas y fluidos), como en los modelos de
+// Minecraft: cajas con esquinas en píxeles, de 0 a 16. Las plantas y el fuego
+// son planos cruzados (`is_cross`).
+
+// Caja de un modelo: esquina mínima y máxima en píxeles
+pub type BlockBox = ([f32; 3], [f32; 3]);
+
+// Altura de la superficie de un bloque de fluido (8/9 de bloque, como en el juego)
+const FLUID_HEIGHT: f32 = 16.0 * 8.0 / 9.0;
+
+// Cajas del bloque según su forma y propiedades. None si es un bloque completo.
+pub fn block_boxes(state: &BlockState) -> Option<Vec<BlockBox>> {
+    let name = state.path();
+    let property = |key: &str| state.properties.get(key).map(String::as_str);
+
+    if matches!(name, "lava" | "water") {
+        return Some(vec![([0.0, 0.0, 0.0], [16.0, FLUID_HEIGHT, 16.0])]);
+    }
+    if matches!(name, "torch" | "soul_torch" | "redstone_torch") {
+        // El recorte de la textura deja solo el palo del centro
+        return Some(vec![([7.0, 0.0, 7.0], [9.0, 10.0, 9.0])]);
+    }
+    if name.ends_with("_slab") {
+        return match property("type") {
+            Some("double") => None,
+            Some("top") => Some(vec![([0.0, 8.0, 0.0], [16.0, 16.0, 16.0])]),
+            _ => Some(vec![([0.0, 0.0, 0.0], [16.0, 8.0, 16.0])]),
+        };
+    }
+    if name.ends_with("_stairs") {
+        return Some(stairs(
+            property("facing").unwrap_or("north"),
+            property("half") == Some("top"),
+            property("shape").unwrap_or("straight"),
+        ));
+    }
+    if name.ends_with("_fence") {
+        // Poste central y dos travesaños hacia cada vecino conectado
+        let post = ([6.0, 0.0, 6.0], [10.0, 16.0, 10.0]);
+        let side = [([7.0, 12.0, 0.0], [9.0, 15.0, 9.0]), ([7.0, 6.0, 0.0], [9.0, 9.0, 9.0])];
+        return Some(connected(state, post, |_| &side));
+    }
+    if name.ends_with("_pane") || name == "iron_bars" {
+        let post = ([7.0, 0.0, 7.0], [9.0, 16.0, 9.0]);
+        let side = [([7.0, 0.0, 0.0], [9.0, 16.0, 7.0])];
+        return Some(connected(state, post, |_| &side));
+    }
+    if name.ends_with("_wall") {
+        let low = [([5.0, 0.0, 0.0], [11.0, 14.0, 8.0])];
+        let tall = [([5.0, 0.0, 0.0], [11.0, 16.0, 8.0])];
+        let mut boxes = connected(state, ([4.0, 0.0, 4.0], [12.0, 16.0, 12.0]), |value| {
+            if value == "tall" { &tall } else { &low }
+        });
+        // Sin `up` el poste central desaparece (muros rectos)
+        if property("up") == Some("false") {
+            boxes.remove(0);
+        }
+        return Some(boxes);
+    }
+    None
+}
+
+// Bloques que se dibujan como dos planos cruzados con recorte por alfa
+pub fn is_cross(state: &BlockState) -> bool {
+    let name = state.path();
+    matches!(
+        name,
+        "nether_sprouts"
+            | "crimson_roots"
+            | "warped_roots"
+            | "crimson_fungus"
+            | "warped_fungus"
+            | "fire"
+            | "soul_fire"
+            | "cobweb"
+            | "dead_bush"
+            | "short_grass"
+            | "grass"
+            | "fern"
+            | "sugar_cane"
+            | "brown_mushroom"
+            | "red_mushroom"
+            | "dandelion"
+            | "poppy"
+            | "blue_orchid"
+            | "allium"
+            | "azure_bluet"
+            | "oxeye_daisy"
+            | "cornflower"
+            | "lily_of_the_valley"
+            | "wither_rose"
+    ) || name.ends_with("_sapling")
+        || name.ends_with("_tulip")
+}
+
+// Escaleras: losa inferior y escalón, descritos mirando al este (sin rotar en
+// Minecraft). Las formas "inner" y "outer" agregan o quitan un cuarto del escalón.
+fn stairs(facing: &str, top: bool, shape: &str) -> Vec<BlockBox> {
+    let mut boxes = vec![([0.0, 0.0, 0.0], [16.0, 8.0, 16.0])];
+    match shape {
+        "outer_left" => boxes.push(([8.0, 8.0, 0.0], [16.0, 16.0, 8.0])),
+        "outer_right" => boxes.push(([8.0, 8.0, 8.0], [16.0, 16.0, 16.0])),
+        "inner_left" => {
+            boxes.push(([8.0, 8.0, 0.0], [16.0, 16.0, 16.0]));
+            boxes.push(([0.0, 8.0, 0.0], [8.0, 16.0, 8.0]));
+        }
+        "inner_right" => {
+            boxes.push(([8.0, 8.0, 0.0], [16.0, 16.0, 16.0]));
+            boxes.push(([0.0, 8.0, 8.0], [8.0, 16.0, 16.0]));
+        }
+        _ => boxes.push(([8.0, 8.0, 0.0], [16.0, 16.0, 16.0])),
+    }
+
+    let turns = match facing {
+        "south" => 1,
+        "west" => 2,
+        "north" => 3,
+        _ => 0,
+    };
+    boxes
+        .into_iter()
+        .map(|b| {
+            let b = rotate_y(b, turns);
+            if top { flip_y(b) } else { b }
+        })
+        .collect()
+}
+
+// Poste más las cajas hacia cada lado conectado. `side` recibe el valor de la
+// propiedad ("true", "low", "tall") y devuelve las cajas del lado norte.
+fn connected<'a>(
+    state: &BlockState,
+    post: BlockBox,
+    side: impl Fn(&str) -> &'a [BlockBox],
+) -> Vec<BlockBox> {
+    let mut boxes = vec![post];
+    for (direction, turns) in [("north", 0), ("east", 1), ("south", 2), ("west", 3)] {
+        match state.properties.get(direction).map(String::as_str) {
+            None | Some("false") | Some("none") => {}
+            Some(value) => boxes.extend(side(value).iter().map(|&b| rotate_y(b, turns))),
+        }
+    }
+    boxes
+}
+
+// Gira una caja alrededor del centro del bloque, en pasos de 90 grados en
+// sentido horario visto desde arriba (el norte pasa al este)
+fn rotate_y((from, to): BlockBox, turns: u32) -> BlockBox {
+    let (mut from, mut to) = (from, to);
+    for _ in 0..turns % 4 {
+        let a = [16.0 - from[2], from[1], from[0]];
+        let b = [16.0 - to[2], to[1], to[0]];
+        from = [a[0].min(b[0]), a[1], a[2].min(b[2])];
+        to = [a[0].max(b[0]), b[1], a[2].max(b[2])];
+    }
+    (from, to)
+}
+
+// Refleja una caja verticalmente (escaleras invertidas)
+fn flip_y((from, to): BlockBox) -> BlockBox {
+    ([from[0], 16.0 - to[1], from[2]], [to[0], 16.0 - from[1], to[2]])
+}
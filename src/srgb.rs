@@ -0,0 +1,48 @@
+use once_cell::sync::Lazy;
+
+// Funciones de transferencia sRGB. Las texturas y los colores de las escenas
+// están en sRGB; el sombreado se hace en lineal y se vuelve a codificar al
+// escribir el framebuffer.
+
+// Tabla de decodificación para los 256 valores de un canal de 8 bits
+static TO_LINEAR: Lazy<[f32; 256]> = Lazy::new(|| {
+    let mut table = [0.0; 256];
+    for (value, linear) in table.iter_mut().enumerate() {
+        *linear = decode(value as f32 / 255.0);
+    }
+    table
+});
+
+fn decode(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn encode(linear: f32) -> f32 {
+    if linear <= 0.0031308 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn to_linear(value: u8) -> f32 {
+    TO_LINEAR[value as usize]
+}
+
+// Codifica un valor lineal en [0, 1] a 8 bits. `dither` se suma antes de
+// cuantizar, en fracciones de un escalón (entre -0.5 y 0.5)
+pub fn to_srgb(linear: f32, dither: f32) -> u8 {
+    (encode(linear.clamp(0.0, 1.0)) * 255.0 + 0.5 + dither).clamp(0.0, 255.0) as u8
+}
+
+// Matriz de Bayer 4x4 para dithering ordenado
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+// Desplazamiento de dithering para el pixel (x, y), en (-0.5, 0.5)
+pub fn dither_offset(x: usize, y: usize) -> f32 {
+    (BAYER[y % 4][x % 4] as f32 + 0.5) / 16.0 - 0.5
+}
@@ -0,0 +1,505 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use nalgebra_glm::{self as glm, Vec3};
+
+use crate::color::Color;
+use crate::cube::Cube;
+use crate::material::Material;
+use crate::nbt::{self, NbtError, Tag};
+use crate::instance::Instance;
+use crate::mesh::CrossedQuads;
+use crate::ray_intersect::RayIntersect;
+use crate::resource_pack::{ModelCube, ResourcePack};
+use crate::shape;
+use crate::texture_registry::{TextureError, TextureRegistry};
+
+// Importador de estructuras de Minecraft: archivos de bloque de estructura
+// (.nbt) y esquemas Sponge (.schem, versiones 1 a 3), ambos NBT con gzip
+
+// Opacidad mínima de un texel para los bloques recortados, como en el juego
+const ALPHA_CUTOFF: f32 = 0.5;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BlockState {
+    pub name: String,
+    pub properties: BTreeMap<String, String>,
+}
+
+impl BlockState {
+    // Interpreta "minecraft:lava[level=0]"; sin namespace se asume "minecraft:"
+    pub fn parse(text: &str) -> BlockState {
+        let (name, properties) = match text.split_once('[') {
+            Some((name, rest)) => (name, rest.trim_end_matches(']')),
+            None => (text, ""),
+        };
+        let properties = properties
+            .split(',')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+            .collect();
+        BlockState {
+            name: normalize_name(name.trim()),
+            properties,
+        }
+    }
+
+    // Entrada de paleta NBT: {Name: "minecraft:lava", Properties: {level: "0"}}
+    pub fn from_nbt(entry: &Tag) -> Result<BlockState, ImportError> {
+        let name = entry
+            .get("Name")
+            .and_then(Tag::as_str)
+            .ok_or_else(|| format_error("palette entry without `Name`"))?;
+        let properties = entry
+            .get("Properties")
+            .and_then(Tag::as_compound)
+            .map(|props| {
+                props
+                    .iter()
+                    .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(BlockState {
+            name: normalize_name(name),
+            properties,
+        })
+    }
+
+    pub fn is_air(&self) -> bool {
+        matches!(
+            self.name.as_str(),
+            "minecraft:air" | "minecraft:cave_air" | "minecraft:void_air" | "minecraft:structure_void"
+        )
+    }
+
+    // Nombre sin namespace ("minecraft:obsidian" -> "obsidian")
+    pub fn path(&self) -> &str {
+        self.name.split_once(':').map_or(self.name.as_str(), |(_, path)| path)
+    }
+}
+
+fn normalize_name(name: &str) -> String {
+    if name.contains(':') {
+        name.to_string()
+    } else {
+        format!("minecraft:{}", name)
+    }
+}
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(std::io::Error),
+    Nbt(NbtError),
+    Format(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImportError::Io(e) => write!(f, "cannot read file: {}", e),
+            ImportError::Nbt(e) => write!(f, "{}", e),
+            ImportError::Format(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<NbtError> for ImportError {
+    fn from(e: NbtError) -> Self {
+        ImportError::Nbt(e)
+    }
+}
+
+// Bloques leídos de un archivo, en coordenadas enteras de bloque
+pub struct Structure {
+    pub blocks: Vec<([i32; 3], BlockState)>,
+}
+
+impl Structure {
+    pub fn load(path: &str) -> Result<Structure, ImportError> {
+        let bytes = fs::read(path).map_err(ImportError::Io)?;
+        let root = nbt::read(&bytes)?;
+
+        let is_schematic = Path::new(path)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("schem") || ext.eq_ignore_ascii_case("schematic"));
+
+        if is_schematic || root.get("Schematic").is_some() || root.get("BlockData").is_some() {
+            Structure::from_sponge(&root)
+        } else {
+            Structure::from_structure_block(&root)
+        }
+    }
+
+    fn from_structure_block(root: &Tag) -> Result<Structure, ImportError> {
+        // Algunas estructuras (p. ej. naufragios) traen varias paletas; se usa la primera
+        let palette = root
+            .get("palette")
+            .or_else(|| root.get("palettes").and_then(|p| p.as_list()).and_then(|p| p.first()))
+            .and_then(Tag::as_list)
+            .ok_or_else(|| format_error("structure file has no `palette`"))?;
+
+        let states = palette.iter().map(BlockState::from_nbt).collect::<Result<Vec<_>, _>>()?;
+
+        let entries = root
+            .get("blocks")
+            .and_then(Tag::as_list)
+            .ok_or_else(|| format_error("structure file has no `blocks`"))?;
+
+        let mut blocks = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let pos = entry
+                .get("pos")
+                .and_then(Tag::as_int_vec)
+                .filter(|pos| pos.len() == 3)
+                .ok_or_else(|| format_error("block entry without a valid `pos`"))?;
+            let state = entry
+                .get("state")
+                .and_then(Tag::as_i64)
+                .ok_or_else(|| format_error("block entry without `state`"))?;
+            let state = states
+                .get(state as usize)
+                .ok_or_else(|| format_error(&format!("block state {} is not in the palette", state)))?;
+            blocks.push(([pos[0] as i32, pos[1] as i32, pos[2] as i32], state.clone()));
+        }
+
+        Ok(Structure { blocks })
+    }
+
+    fn from_sponge(root: &Tag) -> Result<Structure, ImportError> {
+        // v3 anida todo dentro de "Schematic" y los bloques dentro de "Blocks"
+        let schematic = root.get("Schematic").unwrap_or(root);
+        let container = schematic.get("Blocks").unwrap_or(schematic);
+
+        let dimension = |name: &str| {
+            schematic
+                .get(name)
+                .and_then(Tag::as_i64)
+                .map(|v| v as u16 as usize)
+                .ok_or_else(|| format_error(&format!("schematic has no `{}`", name)))
+        };
+        let (width, height, length) = (dimension("Width")?, dimension("Height")?, dimension("Length")?);
+
+        let palette = container
+            .get("Palette")
+            .and_then(Tag::as_compound)
+            .ok_or_else(|| format_error("schematic has no `Palette`"))?;
+        let mut states = HashMap::new();
+        for (text, id) in palette {
+            let id = id
+                .as_i64()
+                .ok_or_else(|| format_error(&format!("palette id for `{}` is not an integer", text)))?;
+            states.insert(id, BlockState::parse(text));
+        }
+
+        let data = container
+            .get("BlockData")
+            .or_else(|| container.get("Data"))
+            .and_then(Tag::as_byte_array)
+            .ok_or_else(|| format_error("schematic has no block data"))?;
+
+        let mut blocks = Vec::new();
+        let mut bytes = data.iter().map(|&b| b as u8);
+        for index in 0..width * height * length {
+            let id = read_varint(&mut bytes)
+                .ok_or_else(|| format_error("block data ends before the schematic volume is filled"))?;
+            let state = states
+                .get(&id)
+                .ok_or_else(|| format_error(&format!("block id {} is not in the palette", id)))?;
+            // Orden YZX: index = (y * length + z) * width + x
+            let x = index % width;
+            let z = (index / width) % length;
+            let y = index / (width * length);
+            blocks.push(([x as i32, y as i32, z as i32], state.clone()));
+        }
+
+        Ok(Structure { blocks })
+    }
+}
+
+fn format_error(message: &str) -> ImportError {
+    ImportError::Format(message.to_string())
+}
+
+fn read_varint(bytes: &mut impl Iterator<Item = u8>) -> Option<i64> {
+    let mut value = 0_i64;
+    for shift in (0..35).step_by(7) {
+        let byte = bytes.next()?;
+        value |= ((byte & 0x7f) as i64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+// Bloques que no se pudieron asociar a un material, con su número de apariciones
+#[derive(Default)]
+pub struct ImportReport {
+    pub blocks: usize,
+    pub unmapped: BTreeMap<String, usize>,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} blocks imported", self.blocks)?;
+        if !self.unmapped.is_empty() {
+            write!(f, ", unmapped block ids:")?;
+            for (name, count) in &self.unmapped {
+                write!(f, " {} ({})", name, count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Geometría de los bloques importados: cubos alineados a los ejes, y aparte
+// lo que no lo es (elementos rotados de los modelos, plantas)
+pub struct ImportedBlocks {
+    pub cubes: Vec<Cube>,
+    pub objects: Vec<Box<dyn RayIntersect>>,
+}
+
+// Traducción de IDs de bloque a materiales
+pub struct BlockMapper<'a> {
+    // Materiales por ID de bloque completo ("minecraft:obsidian") o por nombre sin namespace
+    pub materials: HashMap<String, &'a Material>,
+    // Materiales incluidos para los bloques con textura en assets/
+    pub builtin: &'a HashMap<&'static str, Material>,
+    pub fallback: Material,
+    // Modelos y texturas de resource packs, si la escena los declara
+    pub pack: Option<&'a mut ResourcePack>,
+    pub textures: &'a mut TextureRegistry,
+}
+
+impl BlockMapper<'_> {
+    fn scene_material(&self, state: &BlockState) -> Option<Material> {
+        self.materials
+            .get(&state.name)
+            .or_else(|| self.materials.get(state.path()))
+            .map(|&material| material.clone())
+    }
+
+    // Convierte la estructura en cubos de lado `block_size`, desplazados por `offset`.
+    // Cada bloque usa, en orden: un material de la escena, el modelo del resource
+    // pack, un material incluido, o `fallback`; sin resource pack, la forma del
+    // bloque sale de `shape::block_boxes`.
+    pub fn build_cubes(
+        &mut self,
+        structure: &Structure,
+        offset: Vec3,
+        block_size: f32,
+    ) -> Result<(ImportedBlocks, ImportReport), ImportError> {
+        let mut cubes = Vec::new();
+        let mut objects: Vec<Box<dyn RayIntersect>> = Vec::new();
+        let mut report = ImportReport::default();
+
+        for (pos, state) in &structure.blocks {
+            if state.is_air() {
+                continue;
+            }
+            let min = offset + Vec3::new(pos[0] as f32, pos[1] as f32, pos[2] as f32) * block_size;
+            report.blocks += 1;
+
+            let scene_material = self.scene_material(state);
+            if let (None, Some(pack)) = (&scene_material, self.pack.as_deref_mut()) {
+                let base = self.builtin.get(state.path()).cloned().unwrap_or_else(pack_block_material);
+                if let Some(model) = pack.block_cubes(state, &base, self.textures)? {
+                    for ModelCube { cube, transform } in model {
+                        match transform {
+                            // Los elementos rotados quedan en coordenadas de bloque,
+                            // dentro de una instancia que los lleva al mundo
+                            Some(transform) => {
+                                let to_world =
+                                    glm::translation(&min) * glm::scaling(&Vec3::repeat(block_size)) * transform;
+                                let cube = Cube {
+                                    min: cube.min,
+                                    max: cube.max,
+                                    material: cube.material.clone(),
+                                };
+                                if let Some(instance) = Instance::new(cube, to_world) {
+                                    objects.push(Box::new(instance));
+                                }
+                            }
+                            None => cubes.push(Cube {
+                                min: min + cube.min * block_size,
+                                max: min + cube.max * block_size,
+                                material: cube.material.clone(),
+                            }),
+                        }
+                    }
+                    continue;
+                }
+            }
+
+            let material = match scene_material.or_else(|| self.builtin.get(state.path()).cloned()) {
+                Some(material) => material,
+                None => {
+                    *report.unmapped.entry(state.name.clone()).or_insert(0) += 1;
+                    self.fallback.clone()
+                }
+            };
+
+            if shape::is_cross(state) {
+                let max = min + Vec3::repeat(block_size);
+                objects.push(Box::new(CrossedQuads::new(min, max, material)));
+                continue;
+            }
+
+            // Las formas parciales (losas, escaleras, vallas...) se arman con
+            // varias cajas que recortan la textura en lugar de estirarla
+            match shape::block_boxes(state) {
+                Some(boxes) => cubes.extend(boxes.iter().map(|(from, to)| {
+                    let from = Vec3::new(from[0], from[1], from[2]) / 16.0;
+                    let to = Vec3::new(to[0], to[1], to[2]) / 16.0;
+                    Cube {
+                        min: min + from * block_size,
+                        max: min + to * block_size,
+                        material: material.cropped(&from, &to),
+                    }
+                })),
+                None => cubes.push(Cube {
+                    min,
+                    max: min + Vec3::new(block_size, block_size, block_size),
+                    material,
+                }),
+            }
+        }
+
+        Ok((ImportedBlocks { cubes, objects }, report))
+    }
+}
+
+// Materiales por defecto para los bloques que tienen textura en assets/. Las
+// texturas se buscan en las rutas de la escena; los bloques cuya textura no
+// aparece quedan sin material propio.
+pub fn builtin_materials(textures: &mut TextureRegistry) -> Result<HashMap<&'static str, Material>, TextureError> {
+    let blocks = [
+        ("netherrack", "netherrack.jpg", 30.0, [0.6, 0.2], 0.0, 0.0, 0.0, 0.0),
+        ("obsidian", "obsidian", 50.0, [0.6, 0.5], 0.0, 0.0, 0.0, 0.0),
+        ("crying_obsidian", "ruinedobsidian", 40.0, [0.6, 0.3], 0.0, 0.0, 0.0, 0.0),
+        ("magma_block", "magmablock", 100.0, [0.7, 0.7], 0.1, 0.0, 0.0, 0.5),
+        ("lava", "lava", 100.0, [0.7, 0.9], 0.1, 0.2, 0.3, 1.0),
+        ("sand", "sand", 30.0, [0.8, 0.1], 0.0, 0.0, 0.0, 0.0),
+    ];
+
+    let mut materials = HashMap::new();
+    for (block, texture, spec, albedo, reflectivity, transparency, refraction_index, emission) in blocks {
+        let texture = match textures.load(texture) {
+            Ok(texture) => texture,
+            Err(TextureError::NotFound { .. }) => continue,
+            Err(e) => return Err(e),
+        };
+        let mut material =
+            Material::new_with_texture(spec, albedo, reflectivity, transparency, refraction_index, texture.clone());
+        // La lava y el magma brillan con el color de su propia textura
+        if emission > 0.0 {
+            material = material.with_emission(Color::new(255, 255, 255), emission, Some(texture));
+        }
+        materials.insert(block, material);
+    }
+    Ok(materials)
+}
+
+// Parámetros de sombreado para los bloques de un resource pack sin material
+// propio. Las texturas con partes transparentes (plantas, antorchas) se recortan.
+fn pack_block_material() -> Material {
+    Material::new(Color::new(255, 255, 255), 10.0, [0.9, 0.1], 0.0, 0.0, 0.0).with_alpha_cutoff(ALPHA_CUTOFF)
+}
+
+// Material para bloques desconocidos: magenta, igual que una textura faltante
+pub fn unknown_block_material() -> Material {
+    Material::new(Color::from_hex(0xFF00FF), 10.0, [0.9, 0.1], 0.0, 0.0, 0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compound(entries: Vec<(&str, Tag)>) -> Tag {
+        Tag::Compound(entries.into_iter().map(|(name, tag)| (name.to_string(), tag)).collect())
+    }
+
+    // Volumen de 2x2x2 con un id de dos bytes (200) en la paleta
+    fn sponge_blocks() -> (Tag, Tag) {
+        let palette = compound(vec![
+            ("minecraft:air", Tag::Int(0)),
+            ("minecraft:obsidian", Tag::Int(1)),
+            ("minecraft:lava[level=0]", Tag::Int(200)),
+        ]);
+        // YZX: (0,0,0) (1,0,0) (0,0,1) (1,0,1) (0,1,0) ...
+        let data: Vec<i8> = [1, 0xc8, 0x01, 0, 0, 0, 0, 1, 1]
+            .iter()
+            .map(|&b: &u8| b as i8)
+            .collect();
+        (palette, Tag::ByteArray(data))
+    }
+
+    fn check(structure: &Structure) {
+        let find = |at: [i32; 3]| {
+            structure.blocks.iter().find(|(position, _)| *position == at).map(|(_, state)| state).unwrap()
+        };
+        assert_eq!(structure.blocks.len(), 8);
+        assert_eq!(find([0, 0, 0]).name, "minecraft:obsidian");
+        let lava = find([1, 0, 0]);
+        assert_eq!(lava.name, "minecraft:lava");
+        assert_eq!(lava.properties.get("level").map(String::as_str), Some("0"));
+        assert!(find([0, 0, 1]).is_air());
+        assert_eq!(find([0, 1, 1]).name, "minecraft:obsidian");
+        assert_eq!(find([1, 1, 1]).name, "minecraft:obsidian");
+    }
+
+    #[test]
+    fn decodes_sponge_v2() {
+        let (palette, data) = sponge_blocks();
+        let root = compound(vec![
+            ("Width", Tag::Short(2)),
+            ("Height", Tag::Short(2)),
+            ("Length", Tag::Short(2)),
+            ("Palette", palette),
+            ("BlockData", data),
+        ]);
+        check(&Structure::from_sponge(&root).unwrap());
+    }
+
+    #[test]
+    fn decodes_sponge_v3() {
+        let (palette, data) = sponge_blocks();
+        let blocks = compound(vec![("Palette", palette), ("Data", data)]);
+        let schematic = compound(vec![
+            ("Width", Tag::Short(2)),
+            ("Height", Tag::Short(2)),
+            ("Length", Tag::Short(2)),
+            ("Blocks", blocks),
+        ]);
+        check(&Structure::from_sponge(&compound(vec![("Schematic", schematic)])).unwrap());
+    }
+
+    #[test]
+    fn short_sponge_data_is_an_error() {
+        let (palette, _) = sponge_blocks();
+        // El último byte deja un varint a medias
+        let data = Tag::ByteArray(vec![1, 1, 1, 1, 1, 1, 1, 0xc8_u8 as i8]);
+        let root = compound(vec![
+            ("Width", Tag::Short(2)),
+            ("Height", Tag::Short(2)),
+            ("Length", Tag::Short(2)),
+            ("Palette", palette),
+            ("BlockData", data),
+        ]);
+        assert!(Structure::from_sponge(&root).is_err());
+    }
+
+    #[test]
+    fn reads_varints() {
+        let mut bytes = [0x05, 0xc8, 0x01, 0xff, 0xff, 0x03, 0x80].into_iter();
+        assert_eq!(read_varint(&mut bytes), Some(5));
+        assert_eq!(read_varint(&mut bytes), Some(200));
+        assert_eq!(read_varint(&mut bytes), Some(65535));
+        assert_eq!(read_varint(&mut bytes), None);
+    }
+}
@@ -1,73 +1,295 @@
 extern crate image;
-use image::{ImageReader, Pixel, DynamicImage, GenericImageView};
+use image::{ImageReader, ImageResult, Pixel, DynamicImage, GenericImageView};
 use std::fmt;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::Arc;
 use crate::color::Color;
+use crate::radiance::Radiance;
+use crate::sampler::{Filter, Sampler, Wrap};
+
+// Ticks del juego por segundo: las duraciones de las animaciones van en ticks
+const TICKS_PER_SECOND: f32 = 20.0;
 
 #[derive(Clone)]
 pub struct Texture {
   image: DynamicImage,
   pub width: usize,
   pub height: usize,
-  color_array: Vec<Color>,
+  // Cadena de mipmaps: el nivel 0 es la imagen y cada uno mide la mitad del anterior
+  levels: Vec<Level>,
+  animation: Option<Animation>,
+  // Mapas `_n` (normales) y `_s` (especular) de LabPBR que acompañan a la
+  // textura en un resource pack
+  pub normal_map: Option<TextureHandle>,
+  pub specular_map: Option<TextureHandle>,
 }
 
-impl Texture {
-  pub fn new(file_path: &str) -> Texture {
-    let img = match ImageReader::open(file_path) {
-        Ok(reader) => match reader.decode() {
-            Ok(image) => image,
-            Err(e) => {
-                println!("Error decoding image: {}", e);
-                return Texture::black(); // Usar una textura negra como respaldo
-            }
-        },
-        Err(e) => {
-            println!("Error opening image file: {}", e);
-            return Texture::black(); // Usar una textura negra como respaldo
-        }
+#[derive(Clone)]
+struct Level {
+  width: usize,
+  height: usize,
+  // Texels ya decodificados de sRGB a lineal
+  color_array: Vec<Radiance>,
+  // Opacidad de cada texel, en [0, 1] (1 si la imagen no tiene canal alfa)
+  alpha_array: Vec<f32>,
+}
+
+impl Level {
+  // Nivel siguiente: cada texel promedia un bloque de 2x2 (en lineal)
+  fn half(&self) -> Level {
+    let (width, height) = ((self.width / 2).max(1), (self.height / 2).max(1));
+    let mut level = Level {
+      width,
+      height,
+      color_array: vec![Radiance::black(); width * height],
+      alpha_array: vec![0.0; width * height],
     };
+    for y in 0..height {
+      for x in 0..width {
+        let mut color = Radiance::black();
+        let mut alpha = 0.0;
+        for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+          let sx = (2 * x + dx).min(self.width - 1);
+          let sy = (2 * y + dy).min(self.height - 1);
+          color += self.color_array[sy * self.width + sx];
+          alpha += self.alpha_array[sy * self.width + sx];
+        }
+        level.color_array[y * width + x] = color * 0.25;
+        level.alpha_array[y * width + x] = alpha * 0.25;
+      }
+    }
+    level
+  }
+}
+
+// Animación por cuadros de un archivo `.png.mcmeta`: la imagen es una tira de
+// cuadros (de arriba abajo, o en filas si el cuadro es más angosto que la imagen)
+#[derive(Clone, Debug)]
+pub struct Animation {
+  pub frame_width: usize,
+  pub frame_height: usize,
+  // Índice del cuadro en la tira y duración en ticks, en orden de reproducción
+  pub frames: Vec<(usize, u32)>,
+  // Mezcla cada cuadro con el siguiente en lugar de saltar (lava, agua)
+  pub interpolate: bool,
+}
+
+impl Animation {
+  fn total_ticks(&self) -> u32 {
+    self.frames.iter().map(|&(_, ticks)| ticks).sum()
+  }
+
+  // Cuadro actual, el siguiente y cuánto se avanzó hacia él, en [0, 1)
+  fn frames_at(&self, time: f32) -> (usize, usize, f32) {
+    let total = self.total_ticks().max(1) as f32;
+    let mut tick = (time * TICKS_PER_SECOND).rem_euclid(total);
+    for (i, &(index, ticks)) in self.frames.iter().enumerate() {
+      let ticks = ticks as f32;
+      if tick < ticks || i + 1 == self.frames.len() {
+        let next = self.frames[(i + 1) % self.frames.len()].0;
+        return (index, next, (tick / ticks.max(1.0)).min(1.0));
+      }
+      tick -= ticks;
+    }
+    (0, 0, 0.0)
+  }
+}
+
+impl Texture {
+  // Textura de color: los texels se pasan de sRGB a lineal
+  pub fn load(file_path: &Path) -> ImageResult<Texture> {
+    Texture::load_with(file_path, true)
+  }
+
+  // Textura de datos (normales, alturas): los canales se leen tal cual, en [0, 1]
+  pub fn load_data(file_path: &Path) -> ImageResult<Texture> {
+    Texture::load_with(file_path, false)
+  }
+
+  fn load_with(file_path: &Path, srgb: bool) -> ImageResult<Texture> {
+    let img = ImageReader::open(file_path)?.decode()?;
     let width = img.width() as usize;
     let height = img.height() as usize;
     let mut texture = Texture {
       image: img,
       width,
       height,
-      color_array: vec![Color::black(); width * height],
+      levels: vec![Level {
+        width,
+        height,
+        color_array: vec![Radiance::black(); width * height],
+        alpha_array: vec![1.0; width * height],
+      }],
+      animation: None,
+      normal_map: None,
+      specular_map: None,
     };
-    texture.load_color_array();
-    texture
+    texture.load_color_array(srgb);
+    texture.build_mipmaps();
+    Ok(texture)
   }
 
-  fn load_color_array(&mut self) {
+  fn load_color_array(&mut self, srgb: bool) {
+    let level = &mut self.levels[0];
     for x in 0..self.width {
         for y in 0..self.height {
-            let pixel = self.image.get_pixel(x as u32, y as u32).to_rgb();
-            let color = ((pixel[0] as u32) << 16) | ((pixel[1] as u32) << 8) | (pixel[2] as u32);
-            self.color_array[y * self.width + x] = Color::from_hex(color);
+            let pixel = self.image.get_pixel(x as u32, y as u32).to_rgba();
+            let color = Color { r: pixel[0], g: pixel[1], b: pixel[2] };
+            level.color_array[y * self.width + x] = if srgb {
+              Radiance::from_srgb(color)
+            } else {
+              Radiance { r: pixel[0] as f32 / 255.0, g: pixel[1] as f32 / 255.0, b: pixel[2] as f32 / 255.0 }
+            };
+            // El alfa es lineal, no pasa por la curva sRGB
+            level.alpha_array[y * self.width + x] = pixel[3] as f32 / 255.0;
         }
     }
 }
 
-  pub fn get_color(&self, x: usize, y: usize) -> Color {
-    if x >= self.width || y >= self.height {
-      Color::from_hex(0xFF00FF)
-    } else {
-      self.color_array[y * self.width + x]
+  // Reduce a la mitad hasta llegar a un texel. En las texturas animadas se
+  // para antes si un cuadro ya no se puede dividir sin mezclarse con el vecino.
+  fn build_mipmaps(&mut self) {
+    self.levels.truncate(1);
+    loop {
+      let level = self.levels.len();
+      let (width, height) = self.frame_size(level - 1);
+      let splittable = match self.animation {
+        Some(_) => width % 2 == 0 && height % 2 == 0,
+        None => width > 1 || height > 1,
+      };
+      if !splittable {
+        break;
+      }
+      let next = self.levels[level - 1].half();
+      self.levels.push(next);
     }
   }
 
-  pub fn black() -> Texture {
-    let width = 1; // Ancho de 1 píxel
-    let height = 1; // Alto de 1 píxel
-    let mut texture = Texture {
-        image: DynamicImage::new_rgb8(width as u32, height as u32),
-        width,
-        height,
-        color_array: vec![Color::new(0, 0, 0); width * height], // Colores negros
+  // Anima la textura. Los cuadros que no caben en la imagen se descartan.
+  pub fn with_animation(mut self, mut animation: Animation) -> Texture {
+    if animation.frame_width == 0 || animation.frame_height == 0 {
+      return self;
+    }
+    let columns = self.width / animation.frame_width;
+    let count = columns * (self.height / animation.frame_height);
+    animation.frames.retain(|&(index, _)| index < count);
+    if !animation.frames.is_empty() {
+      self.animation = Some(animation);
+      self.build_mipmaps();
+    }
+    self
+  }
+
+  // Color en coordenadas UV (v = 0 abajo). `time` en segundos elige el cuadro
+  // de las texturas animadas y `footprint` es el ancho del pixel en unidades
+  // UV, con el que el filtrado trilineal elige el mipmap.
+  pub fn sample(&self, u: f32, v: f32, time: f32, sampler: &Sampler, footprint: f32) -> Radiance {
+    let (current, next, t) = self.frames_at(time);
+    let color = |frame: usize| match sampler.filter {
+      Filter::Nearest => self.levels[0].color_array[self.nearest(0, frame, u, v, sampler.wrap)],
+      Filter::Bilinear => self.bilinear(0, frame, u, v, sampler.wrap),
+      Filter::Trilinear => {
+        let (width, height) = self.frame_size(0);
+        let max_level = (self.levels.len() - 1) as f32;
+        let lod = (footprint * width.max(height) as f32).max(f32::MIN_POSITIVE).log2().clamp(0.0, max_level);
+        let lower = lod.floor() as usize;
+        let weight = lod - lower as f32;
+        let color = self.bilinear(lower, frame, u, v, sampler.wrap);
+        if weight > 0.0 {
+          color * (1.0 - weight) + self.bilinear(lower + 1, frame, u, v, sampler.wrap) * weight
+        } else {
+          color
+        }
+      }
     };
-    texture.load_color_array(); // Cargar el color negro
-    texture
-}
+    if t > 0.0 {
+      color(current) * (1.0 - t) + color(next) * t
+    } else {
+      color(current)
+    }
+  }
+
+  // Opacidad en coordenadas UV, siempre del texel más cercano para que el
+  // recorte por alfa quede nítido
+  pub fn alpha(&self, u: f32, v: f32, time: f32, wrap: Wrap) -> f32 {
+    let alpha = |frame: usize| self.levels[0].alpha_array[self.nearest(0, frame, u, v, wrap)];
+    let (current, next, t) = self.frames_at(time);
+    if t > 0.0 {
+      alpha(current) * (1.0 - t) + alpha(next) * t
+    } else {
+      alpha(current)
+    }
+  }
+
+  // Cuadro actual, el siguiente y el peso de este último (0 si la textura no
+  // está animada o no interpola)
+  fn frames_at(&self, time: f32) -> (usize, usize, f32) {
+    match &self.animation {
+      Some(animation) => {
+        let (current, next, t) = animation.frames_at(time);
+        (current, next, if animation.interpolate { t } else { 0.0 })
+      }
+      None => (0, 0, 0.0),
+    }
+  }
+
+  // Tamaño de un cuadro en un nivel (la imagen entera si no está animada)
+  fn frame_size(&self, level: usize) -> (usize, usize) {
+    match &self.animation {
+      Some(animation) => (animation.frame_width >> level, animation.frame_height >> level),
+      None => (self.levels[level].width, self.levels[level].height),
+    }
+  }
+
+  // Posición en el nivel del texel (x, y) de un cuadro, ajustado con `wrap`
+  fn texel_index(&self, level: usize, frame: usize, x: i64, y: i64, wrap: Wrap) -> usize {
+    let (width, height) = self.frame_size(level);
+    let level_width = self.levels[level].width;
+    let columns = (level_width / width).max(1);
+    let x = wrap.texel(x, width) + (frame % columns) * width;
+    let y = wrap.texel(y, height) + (frame / columns) * height;
+    y * level_width + x
+  }
+
+  fn nearest(&self, level: usize, frame: usize, u: f32, v: f32, wrap: Wrap) -> usize {
+    let (width, height) = self.frame_size(level);
+    let x = (u * width as f32).floor() as i64;
+    let y = ((1.0 - v) * height as f32).floor() as i64;
+    self.texel_index(level, frame, x, y, wrap)
+  }
+
+  // Mezcla de los 4 texels cuyos centros rodean el punto
+  fn bilinear(&self, level: usize, frame: usize, u: f32, v: f32, wrap: Wrap) -> Radiance {
+    let (width, height) = self.frame_size(level);
+    let x = u * width as f32 - 0.5;
+    let y = (1.0 - v) * height as f32 - 0.5;
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (x0, y0) = (x0 as i64, y0 as i64);
+
+    let colors = &self.levels[level].color_array;
+    let texel = |dx: i64, dy: i64| colors[self.texel_index(level, frame, x0 + dx, y0 + dy, wrap)];
+    let top = texel(0, 0) * (1.0 - fx) + texel(1, 0) * fx;
+    let bottom = texel(0, 1) * (1.0 - fx) + texel(1, 1) * fx;
+    top * (1.0 - fy) + bottom * fy
+  }
+
+  // Tamaño de un texel del nivel base en unidades UV
+  pub fn texel_size(&self) -> (f32, f32) {
+    let (width, height) = self.frame_size(0);
+    (1.0 / width as f32, 1.0 / height as f32)
+  }
+
+  // Color promedio de la textura, en lineal
+  pub fn average(&self) -> Radiance {
+    let colors = &self.levels[0].color_array;
+    let mut sum = Radiance::black();
+    for &color in colors {
+      sum += color;
+    }
+    sum * (1.0 / colors.len().max(1) as f32)
+  }
 }
 
 impl fmt::Debug for Texture {
@@ -75,6 +297,32 @@ impl fmt::Debug for Texture {
     f.debug_struct("Texture")
       .field("width", &self.width)
       .field("height", &self.height)
+      .field("animation", &self.animation)
       .finish()
   }
-}
\ No newline at end of file
+}
+
+// Referencia compartida a una textura cargada por `TextureRegistry`. Dos
+// handles son iguales si apuntan a la misma textura.
+#[derive(Clone, Debug)]
+pub struct TextureHandle(Arc<Texture>);
+
+impl TextureHandle {
+  pub fn new(texture: Texture) -> TextureHandle {
+    TextureHandle(Arc::new(texture))
+  }
+}
+
+impl PartialEq for TextureHandle {
+  fn eq(&self, other: &Self) -> bool {
+    Arc::ptr_eq(&self.0, &other.0)
+  }
+}
+
+impl Deref for TextureHandle {
+  type Target = Texture;
+
+  fn deref(&self) -> &Texture {
+    &self.0
+  }
+}
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::texture::{Animation, Texture, TextureHandle};
+
+// Extensiones probadas, en orden, cuando el nombre no trae una
+const EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+#[derive(Debug)]
+pub enum TextureError {
+    NotFound { name: String, searched: Vec<PathBuf> },
+    Decode { path: PathBuf, source: image::ImageError },
+    Animation { path: PathBuf, source: serde_json::Error },
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureError::NotFound { name, searched } => {
+                write!(f, "texture `{}` not found (searched:", name)?;
+                for path in searched {
+                    write!(f, " {}", path.display())?;
+                }
+                write!(f, ")")
+            }
+            TextureError::Decode { path, source } => write!(f, "cannot load texture {}: {}", path.display(), source),
+            TextureError::Animation { path, source } => write!(f, "invalid animation {}: {}", path.display(), source),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+// Carga texturas por nombre o ruta y entrega handles compartidos. Cada archivo
+// se decodifica una sola vez aunque se pida con nombres distintos.
+//
+// Un nombre se busca en cada carpeta registrada, en orden:
+//   "lava"                 -> <carpeta>/lava.png, o dentro de un resource pack
+//                             <carpeta>/assets/minecraft/textures/block/lava.png
+//   "minecraft:block/lava" -> <carpeta>/assets/minecraft/textures/block/lava.png
+//   "lava.jpg"             -> <carpeta>/lava.jpg
+// Una ruta a un archivo existente se usa tal cual. Si junto a la imagen hay un
+// `.mcmeta` (por ejemplo lava.png.mcmeta), la textura se anima, y los `_n.png`
+// y `_s.png` de LabPBR (stone_n.png, stone_s.png) se usan como sus mapas de
+// normales y especular.
+pub struct TextureRegistry {
+    search_paths: Vec<PathBuf>,
+    // Las claves indican si la textura se cargó como datos (sin sRGB)
+    by_name: HashMap<(String, bool), TextureHandle>,
+    by_path: HashMap<(PathBuf, bool), TextureHandle>,
+}
+
+impl TextureRegistry {
+    pub fn new(search_paths: Vec<PathBuf>) -> TextureRegistry {
+        TextureRegistry {
+            search_paths,
+            by_name: HashMap::new(),
+            by_path: HashMap::new(),
+        }
+    }
+
+    // Textura de color
+    pub fn load(&mut self, name: &str) -> Result<TextureHandle, TextureError> {
+        self.load_as(name, false)
+    }
+
+    // Textura de datos (normales, alturas): los canales no pasan por sRGB
+    pub fn load_data(&mut self, name: &str) -> Result<TextureHandle, TextureError> {
+        self.load_as(name, true)
+    }
+
+    fn load_as(&mut self, name: &str, data: bool) -> Result<TextureHandle, TextureError> {
+        let name_key = (name.to_string(), data);
+        if let Some(handle) = self.by_name.get(&name_key) {
+            return Ok(handle.clone());
+        }
+
+        let candidates = self.candidates(name);
+        let path = candidates.iter().find(|path| path.is_file()).ok_or_else(|| TextureError::NotFound {
+            name: name.to_string(),
+            searched: candidates.clone(),
+        })?;
+
+        // La ruta canónica identifica el archivo sin importar cómo se escribió
+        let key = (fs::canonicalize(path).unwrap_or_else(|_| path.clone()), data);
+        let handle = match self.by_path.get(&key) {
+            Some(handle) => handle.clone(),
+            None => {
+                let load = if data { Texture::load_data } else { Texture::load };
+                let mut texture = load(path).map_err(|source| TextureError::Decode {
+                    path: path.clone(),
+                    source,
+                })?;
+                if let Some(animation) = load_animation(path, &texture)? {
+                    texture = texture.with_animation(animation);
+                }
+                if !data {
+                    texture.normal_map = self.companion(path, "_n")?;
+                    texture.specular_map = self.companion(path, "_s")?;
+                }
+                let handle = TextureHandle::new(texture);
+                self.by_path.insert(key, handle.clone());
+                handle
+            }
+        };
+
+        self.by_name.insert(name_key, handle.clone());
+        Ok(handle)
+    }
+
+    // Mapa de datos que acompaña a una imagen con el sufijo de LabPBR
+    // (`stone.png` -> `stone_n.png`), si existe
+    fn companion(&mut self, image_path: &Path, suffix: &str) -> Result<Option<TextureHandle>, TextureError> {
+        let Some(stem) = image_path.file_stem() else {
+            return Ok(None);
+        };
+        let path = image_path.with_file_name(format!("{}{}.png", stem.to_string_lossy(), suffix));
+        if !path.is_file() {
+            return Ok(None);
+        }
+        self.load_data(&path.to_string_lossy()).map(Some)
+    }
+
+    fn candidates(&self, name: &str) -> Vec<PathBuf> {
+        let mut candidates = Vec::new();
+        if Path::new(name).is_file() {
+            candidates.push(PathBuf::from(name));
+            return candidates;
+        }
+
+        let (namespace, path) = name.split_once(':').unwrap_or(("minecraft", name));
+        let has_extension = Path::new(path).extension().is_some();
+        let with_extensions = |base: PathBuf| -> Vec<PathBuf> {
+            if has_extension {
+                vec![base]
+            } else {
+                EXTENSIONS.iter().map(|ext| base.with_extension(ext)).collect()
+            }
+        };
+
+        for dir in &self.search_paths {
+            let pack_textures = dir.join("assets").join(namespace).join("textures");
+            if !name.contains(':') {
+                candidates.extend(with_extensions(dir.join(path)));
+            }
+            candidates.extend(with_extensions(pack_textures.join(path)));
+            if !path.contains('/') {
+                candidates.extend(with_extensions(pack_textures.join("block").join(path)));
+            }
+        }
+        candidates
+    }
+}
+
+// Archivo `.mcmeta` de una textura animada de Minecraft
+#[derive(Deserialize)]
+struct Mcmeta {
+    animation: Option<AnimationDef>,
+}
+
+#[derive(Deserialize)]
+struct AnimationDef {
+    #[serde(default)]
+    interpolate: bool,
+    width: Option<usize>,
+    height: Option<usize>,
+    // Duración por defecto de cada cuadro, en ticks
+    #[serde(default = "default_frametime")]
+    frametime: u32,
+    // Orden de los cuadros; por defecto todos los de la tira, en orden
+    frames: Option<Vec<FrameDef>>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FrameDef {
+    Index(usize),
+    Timed { index: usize, time: Option<u32> },
+}
+
+fn default_frametime() -> u32 {
+    1
+}
+
+// Animación descrita en `<imagen>.mcmeta`, si el archivo existe
+fn load_animation(image_path: &Path, texture: &Texture) -> Result<Option<Animation>, TextureError> {
+    let mut path = image_path.as_os_str().to_owned();
+    path.push(".mcmeta");
+    let path = PathBuf::from(path);
+    let Ok(source) = fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+    let meta: Mcmeta = serde_json::from_str(&source).map_err(|source| TextureError::Animation {
+        path: path.clone(),
+        source,
+    })?;
+    let Some(def) = meta.animation else {
+        return Ok(None);
+    };
+
+    // Sin tamaño explícito los cuadros son cuadrados, como en el juego
+    let (frame_width, frame_height) = match (def.width, def.height) {
+        (Some(width), height) => (width, height.unwrap_or(texture.height)),
+        (None, Some(height)) => (texture.width, height),
+        (None, None) => {
+            let side = texture.width.min(texture.height);
+            (side, side)
+        }
+    };
+    let frames = match def.frames {
+        Some(frames) => frames
+            .into_iter()
+            .map(|frame| match frame {
+                FrameDef::Index(index) => (index, def.frametime),
+                FrameDef::Timed { index, time } => (index, time.unwrap_or(def.frametime)),
+            })
+            .collect(),
+        None => {
+            let count = (texture.width / frame_width.max(1)) * (texture.height / frame_height.max(1));
+            (0..count).map(|index| (index, def.frametime)).collect()
+        }
+    };
+
+    Ok(Some(Animation {
+        frame_width,
+        frame_height,
+        frames,
+        interpolate: def.interpolate,
+    }))
+}
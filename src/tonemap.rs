@@ -0,0 +1,66 @@
+use crate::radiance::Radiance;
+
+// Conversión de radiancia HDR a valores de pantalla lineales en [0, 1]. Solo
+// se aplica al final, una vez por pixel; todo el sombreado trabaja con `Radiance`.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToneMapper {
+    // Recorta a [0, 1], igual que el pipeline de 8 bits anterior
+    Clamp,
+    Reinhard,
+    // Ajuste de ACES filmic de Krzysztof Narkowicz
+    Aces,
+}
+
+impl ToneMapper {
+    pub fn parse(name: &str) -> Option<ToneMapper> {
+        match name {
+            "clamp" | "none" => Some(ToneMapper::Clamp),
+            "reinhard" => Some(ToneMapper::Reinhard),
+            "aces" => Some(ToneMapper::Aces),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ToneMapper::Clamp => "clamp",
+            ToneMapper::Reinhard => "reinhard",
+            ToneMapper::Aces => "aces",
+        }
+    }
+
+    // Siguiente operador, para alternar desde la ventana
+    pub fn next(self) -> ToneMapper {
+        match self {
+            ToneMapper::Clamp => ToneMapper::Reinhard,
+            ToneMapper::Reinhard => ToneMapper::Aces,
+            ToneMapper::Aces => ToneMapper::Clamp,
+        }
+    }
+
+    fn apply(self, value: f32) -> f32 {
+        match self {
+            ToneMapper::Clamp => value,
+            ToneMapper::Reinhard => value / (1.0 + value),
+            ToneMapper::Aces => {
+                let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+                (value * (a * value + b)) / (value * (c * value + d) + e)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ToneMapping {
+    pub operator: ToneMapper,
+    // Exposición en pasos (EV): cada paso duplica la radiancia
+    pub exposure: f32,
+}
+
+impl ToneMapping {
+    pub fn map(&self, radiance: Radiance) -> Radiance {
+        let scale = self.exposure.exp2();
+        radiance.map(|v| self.operator.apply((v * scale).max(0.0)).clamp(0.0, 1.0))
+    }
+}
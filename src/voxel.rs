@@ -0,0 +1,383 @@
+use nalgebra_glm::Vec3;
+
+use crate::aabb::Aabb;
+use crate::cube::Cube;
+use crate::ray_intersect::{Intersect, RayIntersect};
+
+const EPSILON: f32 = 1e-4;
+// Máximo de celdas de la rejilla densa (32 MiB de IDs); si los bloques están
+// más dispersos, quedan todos libres para la BVH
+const MAX_CELLS: usize = 1 << 24;
+
+// Rejilla uniforme de bloques recorrida con DDA 3D (Amanatides–Woo).
+// Cada celda guarda un ID de bloque; 0 significa celda vacía y el ID `n`
+// corresponde a `palette[n - 1]`.
+pub struct VoxelWorld {
+    origin: Vec3,
+    cell_size: f32,
+    dims: [usize; 3],
+    cells: Vec<u16>,
+    // Bloques en coordenadas locales de la celda (desde 0 hasta `cell_size`),
+    // así un bloque más bajo como la lava ocupa solo parte de su celda
+    palette: Vec<Cube>,
+}
+
+impl VoxelWorld {
+    pub fn new(origin: Vec3, cell_size: f32, dims: [usize; 3]) -> Self {
+        VoxelWorld {
+            origin,
+            cell_size,
+            dims,
+            cells: vec![0; dims[0] * dims[1] * dims[2]],
+            palette: Vec::new(),
+        }
+    }
+
+    // Reparte los cubos entre la rejilla y una lista de cubos libres: los que
+    // caben dentro de una sola celda (vacía) van a la rejilla, el resto se devuelve
+    pub fn from_cubes(cubes: Vec<Cube>, origin: Vec3, cell_size: f32) -> (VoxelWorld, Vec<Cube>) {
+        let mut placed = Vec::new();
+        let mut free = Vec::new();
+        let mut min_cell = [i64::MAX; 3];
+        let mut max_cell = [i64::MIN; 3];
+
+        for cube in cubes {
+            match Self::cell_of(&cube, origin, cell_size) {
+                Some(cell) => {
+                    for axis in 0..3 {
+                        min_cell[axis] = min_cell[axis].min(cell[axis]);
+                        max_cell[axis] = max_cell[axis].max(cell[axis]);
+                    }
+                    placed.push((cell, cube));
+                }
+                None => free.push(cube),
+            }
+        }
+
+        if placed.is_empty() {
+            return (VoxelWorld::new(origin, cell_size, [0, 0, 0]), free);
+        }
+
+        let grid_origin = origin
+            + Vec3::new(min_cell[0] as f32, min_cell[1] as f32, min_cell[2] as f32) * cell_size;
+        let dims = [
+            (max_cell[0] - min_cell[0] + 1) as usize,
+            (max_cell[1] - min_cell[1] + 1) as usize,
+            (max_cell[2] - min_cell[2] + 1) as usize,
+        ];
+        let cell_count = dims[0].checked_mul(dims[1]).and_then(|count| count.checked_mul(dims[2]));
+        if cell_count.is_none_or(|count| count > MAX_CELLS) {
+            free.extend(placed.into_iter().map(|(_, cube)| cube));
+            return (VoxelWorld::new(origin, cell_size, [0, 0, 0]), free);
+        }
+        let mut world = VoxelWorld::new(grid_origin, cell_size, dims);
+
+        for (cell, cube) in placed {
+            let cell = [
+                (cell[0] - min_cell[0]) as usize,
+                (cell[1] - min_cell[1]) as usize,
+                (cell[2] - min_cell[2]) as usize,
+            ];
+            let index = world.index(cell);
+            if world.cells[index] != 0 {
+                // Dos cubos en la misma celda: el segundo queda libre
+                free.push(cube);
+                continue;
+            }
+            let cell_min = world.cell_min(cell);
+            let block = Cube {
+                min: cube.min - cell_min,
+                max: cube.max - cell_min,
+                material: cube.material,
+            };
+            match world.add_block(block) {
+                Ok(id) => world.cells[index] = id,
+                Err(block) => {
+                    // Paleta llena: el cubo vuelve a coordenadas de mundo
+                    free.push(Cube {
+                        min: block.min + cell_min,
+                        max: block.max + cell_min,
+                        material: block.material,
+                    });
+                }
+            }
+        }
+
+        (world, free)
+    }
+
+    // Registra un bloque (en coordenadas locales de celda) y devuelve su ID,
+    // reutilizando el existente si ya hay uno idéntico en la paleta.
+    // Si la paleta está llena devuelve el bloque sin registrarlo.
+    pub fn add_block(&mut self, block: Cube) -> Result<u16, Box<Cube>> {
+        if let Some(position) = self.palette.iter().position(|b| {
+            b.material == block.material
+                && (b.min - block.min).abs().max() < EPSILON
+                && (b.max - block.max).abs().max() < EPSILON
+        }) {
+            return Ok(position as u16 + 1);
+        }
+        if self.palette.len() >= u16::MAX as usize {
+            return Err(Box::new(block));
+        }
+        self.palette.push(block);
+        Ok(self.palette.len() as u16)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.iter().all(|&id| id == 0)
+    }
+
+    fn index(&self, cell: [usize; 3]) -> usize {
+        (cell[1] * self.dims[2] + cell[2]) * self.dims[0] + cell[0]
+    }
+
+    fn cell_min(&self, cell: [usize; 3]) -> Vec3 {
+        self.origin + Vec3::new(cell[0] as f32, cell[1] as f32, cell[2] as f32) * self.cell_size
+    }
+
+    // Celda (en la rejilla infinita con origen `origin`) que contiene por completo al cubo
+    fn cell_of(cube: &Cube, origin: Vec3, cell_size: f32) -> Option<[i64; 3]> {
+        let mut cell = [0; 3];
+        for axis in 0..3 {
+            let index = ((cube.min[axis] - origin[axis]) / cell_size + EPSILON).floor();
+            let cell_start = origin[axis] + index * cell_size;
+            if cube.max[axis] > cell_start + cell_size + EPSILON {
+                return None;
+            }
+            cell[axis] = index as i64;
+        }
+        Some(cell)
+    }
+
+    // Recorre las celdas que atraviesa el rayo en orden, hasta que `visit`
+    // devuelva un resultado o el rayo supere `max_distance`
+    fn traverse<R, F>(&self, ray_origin: &Vec3, ray_direction: &Vec3, max_distance: f32, mut visit: F) -> Option<R>
+    where
+        F: FnMut(&Cube, Vec3) -> Option<R>,
+    {
+        if self.palette.is_empty() {
+            return None;
+        }
+
+        let inv_direction = Vec3::new(1.0 / ray_direction.x, 1.0 / ray_direction.y, 1.0 / ray_direction.z);
+        let t_enter = self.bounds().hit(ray_origin, &inv_direction, max_distance)?;
+        let entry = ray_origin + ray_direction * t_enter;
+
+        let mut cell = [0_i64; 3];
+        let mut step = [0_i64; 3];
+        let mut t_max = [f32::INFINITY; 3];
+        let mut t_delta = [f32::INFINITY; 3];
+
+        for axis in 0..3 {
+            let local = ((entry[axis] - self.origin[axis]) / self.cell_size).floor() as i64;
+            cell[axis] = local.clamp(0, self.dims[axis] as i64 - 1);
+
+            if ray_direction[axis] > 0.0 {
+                step[axis] = 1;
+                let boundary = self.origin[axis] + (cell[axis] + 1) as f32 * self.cell_size;
+                t_max[axis] = (boundary - ray_origin[axis]) * inv_direction[axis];
+                t_delta[axis] = self.cell_size * inv_direction[axis];
+            } else if ray_direction[axis] < 0.0 {
+                step[axis] = -1;
+                let boundary = self.origin[axis] + cell[axis] as f32 * self.cell_size;
+                t_max[axis] = (boundary - ray_origin[axis]) * inv_direction[axis];
+                t_delta[axis] = -self.cell_size * inv_direction[axis];
+            }
+        }
+
+        loop {
+            let current = [cell[0] as usize, cell[1] as usize, cell[2] as usize];
+            let id = self.cells[self.index(current)];
+            if id != 0 {
+                let block = &self.palette[id as usize - 1];
+                if let Some(result) = visit(block, self.cell_min(current)) {
+                    return Some(result);
+                }
+            }
+
+            // Avanzar por el eje cuyo borde de celda está más cerca
+            let axis = if t_max[0] < t_max[1] {
+                if t_max[0] < t_max[2] { 0 } else { 2 }
+            } else if t_max[1] < t_max[2] {
+                1
+            } else {
+                2
+            };
+
+            if t_max[axis] > max_distance {
+                return None;
+            }
+            cell[axis] += step[axis];
+            if cell[axis] < 0 || cell[axis] >= self.dims[axis] as i64 {
+                return None;
+            }
+            t_max[axis] += t_delta[axis];
+        }
+    }
+}
+
+impl RayIntersect for VoxelWorld {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3, time: f32) -> Intersect {
+        // Los bloques nunca salen de su celda, así que el primer bloque
+        // tocado en el orden del recorrido es el más cercano
+        self.traverse(ray_origin, ray_direction, f32::INFINITY, |block, cell_min| {
+            let local_origin = ray_origin - cell_min;
+            let mut i = block.ray_intersect(&local_origin, ray_direction, time);
+            if i.is_intersecting {
+                i.point += cell_min;
+                Some(i)
+            } else {
+                None
+            }
+        })
+        .unwrap_or_else(Intersect::empty)
+    }
+
+    fn bounds(&self) -> Aabb {
+        let size = Vec3::new(self.dims[0] as f32, self.dims[1] as f32, self.dims[2] as f32) * self.cell_size;
+        Aabb::new(self.origin, self.origin + size)
+    }
+
+    fn any_hit(&self, ray_origin: &Vec3, ray_direction: &Vec3, max_distance: f32, time: f32) -> bool {
+        self.traverse(ray_origin, ray_direction, max_distance, |block, cell_min| {
+            let local_origin = ray_origin - cell_min;
+            if block.any_hit(&local_origin, ray_direction, max_distance, time) {
+                Some(())
+            } else {
+                None
+            }
+        })
+        .is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bvh::Bvh;
+    use crate::color::Color;
+    use crate::material::Material;
+
+    // Generador reproducible para los casos de prueba (congruencial de 32 bits)
+    struct Rng(u32);
+
+    impl Rng {
+        fn new(seed: u32) -> Rng {
+            Rng(seed.wrapping_mul(2654435761) ^ 0x9e37_79b9)
+        }
+
+        // Número en [0, 1)
+        fn next_f32(&mut self) -> f32 {
+            self.0 = self.0.wrapping_mul(747796405).wrapping_add(2891336453);
+            (self.0 >> 8) as f32 / (1 << 24) as f32
+        }
+    }
+
+    const CELL: f32 = 0.5;
+
+    // Bloques llenos y bloques bajos (como la lava) en una rejilla de 8x6x8
+    // celdas con origen desplazado, generados igual para ambas estructuras
+    fn blocks(seed: u32) -> Vec<Cube> {
+        let mut rng = Rng::new(seed);
+        let origin = Vec3::new(-1.25, 0.5, 0.75);
+        let mut cubes = Vec::new();
+        for x in 0..8 {
+            for y in 0..6 {
+                for z in 0..8 {
+                    if rng.next_f32() > 0.3 {
+                        continue;
+                    }
+                    let min = origin + Vec3::new(x as f32, y as f32, z as f32) * CELL;
+                    let height = if rng.next_f32() < 0.3 { CELL * 0.875 } else { CELL };
+                    cubes.push(Cube {
+                        min,
+                        max: min + Vec3::new(CELL, height, CELL),
+                        material: Material::new(Color::new(255, 255, 255), 10.0, [0.9, 0.1], 0.0, 0.0, 0.0),
+                    });
+                }
+            }
+        }
+        cubes
+    }
+
+    fn world_and_reference(seed: u32) -> (VoxelWorld, Bvh<Cube>) {
+        let (world, free) = VoxelWorld::from_cubes(blocks(seed), Vec3::new(-1.25, 0.5, 0.75), CELL);
+        assert!(free.is_empty(), "every block fits in a cell");
+        (world, Bvh::new(blocks(seed)))
+    }
+
+    fn assert_same_hit(world: &VoxelWorld, reference: &Bvh<Cube>, origin: &Vec3, direction: &Vec3) {
+        let expected = reference.ray_intersect(origin, direction, 0.0);
+        let hit = world.ray_intersect(origin, direction, 0.0);
+        assert_eq!(hit.is_intersecting, expected.is_intersecting, "ray {:?} {:?}", origin, direction);
+        if expected.is_intersecting {
+            assert!((hit.distance - expected.distance).abs() < 1e-4, "{} != {}", hit.distance, expected.distance);
+            assert!((hit.point - expected.point).magnitude() < 1e-3, "{:?} != {:?}", hit.point, expected.point);
+        }
+
+        for max_distance in [0.3, 1.0, 3.0] {
+            let occluded = expected.is_intersecting && expected.distance < max_distance;
+            assert_eq!(world.any_hit(origin, direction, max_distance, 0.0), occluded);
+        }
+    }
+
+    fn random_vec(rng: &mut Rng) -> Vec3 {
+        Vec3::new(rng.next_f32(), rng.next_f32(), rng.next_f32())
+    }
+
+    #[test]
+    fn matches_bvh_from_outside_and_inside_the_grid() {
+        let (world, reference) = world_and_reference(3);
+        let mut rng = Rng::new(5);
+        for _ in 0..3000 {
+            // Desde afuera de la rejilla y desde celdas (vacías u ocupadas) dentro de ella
+            let origin = Vec3::new(-3.0, -1.0, -1.5) + random_vec(&mut rng).component_mul(&Vec3::new(8.5, 6.0, 8.5));
+            let direction = (random_vec(&mut rng) * 2.0 - Vec3::repeat(1.0)).normalize();
+            assert_same_hit(&world, &reference, &origin, &direction);
+        }
+    }
+
+    #[test]
+    fn matches_bvh_for_axis_parallel_rays() {
+        let (world, reference) = world_and_reference(9);
+        let mut rng = Rng::new(13);
+        for _ in 0..500 {
+            let origin = Vec3::new(-2.0, 0.0, 0.0) + random_vec(&mut rng) * 6.0;
+            for axis in 0..3 {
+                for sign in [-1.0, 1.0] {
+                    let mut direction = Vec3::zeros();
+                    direction[axis] = sign;
+                    assert_same_hit(&world, &reference, &origin, &direction);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn blocks_that_span_cells_stay_free() {
+        let material = Material::new(Color::new(255, 255, 255), 10.0, [0.9, 0.1], 0.0, 0.0, 0.0);
+        let cube = Cube { min: Vec3::new(0.25, 0.0, 0.0), max: Vec3::new(0.75, 0.5, 0.5), material };
+        let (world, free) = VoxelWorld::from_cubes(vec![cube], Vec3::zeros(), CELL);
+        assert!(world.is_empty());
+        assert_eq!(free.len(), 1);
+    }
+
+    #[test]
+    fn sparse_blocks_skip_the_grid() {
+        let material = Material::new(Color::new(255, 255, 255), 10.0, [0.9, 0.1], 0.0, 0.0, 0.0);
+        let block = |x: f32| Cube {
+            min: Vec3::new(x, x, x),
+            max: Vec3::new(x + CELL, x + CELL, x + CELL),
+            material: material.clone(),
+        };
+        // 4000 celdas por eje no caben en la rejilla densa
+        let (world, free) = VoxelWorld::from_cubes(vec![block(0.0), block(2000.0)], Vec3::zeros(), CELL);
+        assert!(world.is_empty());
+        assert_eq!(free.len(), 2);
+
+        let (world, free) = VoxelWorld::from_cubes(vec![block(0.0), block(4.0)], Vec3::zeros(), CELL);
+        assert!(!world.is_empty());
+        assert!(free.is_empty());
+    }
+}